@@ -0,0 +1,133 @@
+// Бенчмарк добавления заказа в PostgreSQL для заказов с 1, 10 и 100 товарами: текущая запись товаров
+// одним запросом (db::add_order) и прежняя запись по одному запросу на товар для сравнения.
+// База данных задается переменной окружения DATABASE_URL, схема должна быть создана миграциями.
+// Каждая итерация добавляет новый заказ, поэтому запускать бенчмарк стоит на отдельной базе данных
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use tokio_postgres::Client;
+
+use rust_project_l0::db::{self, StatementCache};
+use rust_project_l0::error::Error;
+use rust_project_l0::model::Order;
+use rust_project_l0::pool;
+use rust_project_l0::tls::DbTls;
+
+// Количество товаров в заказе
+const ITEM_COUNTS: [usize; 3] = [1, 10, 100];
+
+// Заказ из тестовых данных с заданным UID и количеством товаров
+fn order(order_uid: &str, item_count: usize) -> Order {
+    let mut order: Order = serde_json::from_str(include_str!("../test/model.json")).unwrap();
+    order.order_uid = order_uid.to_string();
+    order.payment.transaction = order_uid.to_string();
+    let item = order.items[0].clone();
+    order.items = (0..item_count as i64)
+        .map(|i| {
+            let mut item = item.clone();
+            item.chrt_id += i;
+            item
+        })
+        .collect();
+    order
+}
+
+// Прежний способ записи заказа: доставка, оплата и информация о заказе пишутся через db::add_order
+// (в точке сохранения транзакции), а каждый товар - отдельным INSERT с получением ID товара
+// и отдельным INSERT связи с заказом, запросы не кэшируются
+async fn add_order_per_item(order: &Order, client: &mut Client, statements: &StatementCache) -> Result<(), Error> {
+    let mut transaction = client.transaction().await?;
+
+    let header = Order { items: Vec::new(), ..order.clone() };
+    db::add_order(&header, &mut transaction, statements).await?;
+
+    for item in &order.items {
+        let row = transaction.query_one(r#"
+            INSERT INTO item (
+                chrt_id, track_number, price, rid, name, sale, size, total_price, nm_id, brand, status
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING item_id
+        "#, &[
+            &item.chrt_id,
+            &item.track_number,
+            &item.price,
+            &item.rid,
+            &item.name,
+            &item.sale,
+            &item.size,
+            &item.total_price,
+            &item.nm_id,
+            &item.brand,
+            &item.status,
+        ]).await?;
+        let item_id: i64 = row.get(0);
+
+        transaction.execute(
+            "INSERT INTO order_item (order_uid, item_id) VALUES ($1, $2)",
+            &[&order.order_uid, &item_id],
+        ).await?;
+    }
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+// Способ записи товаров заказа
+#[derive(Clone, Copy)]
+enum Method {
+    // Один запрос на все товары (db::add_order)
+    Batch,
+    // По одному запросу на товар
+    PerItem,
+}
+
+fn add_order(c: &mut Criterion) {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping add_order benchmark");
+        return;
+    };
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut client = runtime
+        .block_on(pool::connect(&database_url, &DbTls::Disabled))
+        .expect("Failed to connect to the database");
+    let statements = StatementCache::default();
+
+    // UID заказов уникальны между запусками бенчмарка
+    let run = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+    let mut sequence = 0;
+
+    let mut group = c.benchmark_group("add_order");
+    group.measurement_time(Duration::from_secs(10));
+    for (name, method) in [("batch", Method::Batch), ("per_item", Method::PerItem)] {
+        for item_count in ITEM_COUNTS {
+            group.bench_with_input(BenchmarkId::new(name, item_count), &item_count, |b, &item_count| {
+                b.iter_custom(|iters| {
+                    // Заказы создаются до начала замера
+                    let orders: Vec<Order> = (0..iters)
+                        .map(|_| {
+                            sequence += 1;
+                            order(&format!("bench-{}-{}", run, sequence), item_count)
+                        })
+                        .collect();
+
+                    runtime.block_on(async {
+                        let start = Instant::now();
+                        for order in &orders {
+                            match method {
+                                Method::Batch => db::add_order(order, &mut client, &statements).await.map(|_| ()),
+                                Method::PerItem => add_order_per_item(order, &mut client, &statements).await,
+                            }.expect("Failed to add order");
+                        }
+                        start.elapsed()
+                    })
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_order);
+criterion_main!(benches);
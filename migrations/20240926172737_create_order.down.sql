@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS order_item;
+DROP TABLE IF EXISTS item;
+DROP TABLE IF EXISTS order_info;
+DROP TABLE IF EXISTS payment;
+DROP TABLE IF EXISTS delivery;
@@ -0,0 +1,11 @@
+-- Возврат к глобальному ключу товара chrt_id (невозможен, если один chrt_id входит в несколько заказов)
+ALTER TABLE order_item DROP CONSTRAINT order_item_pkey;
+ALTER TABLE order_item ADD COLUMN item_chrt_id BIGINT;
+UPDATE order_item oi SET item_chrt_id = i.chrt_id FROM item i WHERE i.item_id = oi.item_id;
+ALTER TABLE order_item DROP COLUMN item_id;
+
+ALTER TABLE item DROP COLUMN item_id;
+ALTER TABLE item ADD PRIMARY KEY (chrt_id);
+
+ALTER TABLE order_item ADD FOREIGN KEY (item_chrt_id) REFERENCES item ON DELETE CASCADE;
+ALTER TABLE order_item ADD PRIMARY KEY (order_uid, item_chrt_id);
@@ -0,0 +1,12 @@
+-- Товары хранятся отдельно для каждого заказа: один и тот же chrt_id может входить в разные заказы
+ALTER TABLE order_item DROP CONSTRAINT order_item_pkey;
+ALTER TABLE order_item DROP CONSTRAINT order_item_item_chrt_id_fkey;
+ALTER TABLE item DROP CONSTRAINT item_pkey;
+
+ALTER TABLE item ADD COLUMN item_id BIGSERIAL PRIMARY KEY;
+
+ALTER TABLE order_item ADD COLUMN item_id BIGINT REFERENCES item ON DELETE CASCADE;
+UPDATE order_item oi SET item_id = i.item_id FROM item i WHERE i.chrt_id = oi.item_chrt_id;
+ALTER TABLE order_item ALTER COLUMN item_id SET NOT NULL;
+ALTER TABLE order_item DROP COLUMN item_chrt_id;
+ALTER TABLE order_item ADD PRIMARY KEY (order_uid, item_id);
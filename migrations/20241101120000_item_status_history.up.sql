@@ -0,0 +1,12 @@
+-- История изменения статусов товаров. Товар определяется заказом и chrt_id,
+-- поэтому история сохраняется при замене набора товаров заказа
+CREATE TABLE IF NOT EXISTS item_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    order_uid VARCHAR(255) NOT NULL REFERENCES order_info ON DELETE CASCADE,
+    chrt_id BIGINT NOT NULL,
+    old_status INTEGER,
+    new_status INTEGER NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS item_status_history_item_idx ON item_status_history (order_uid, chrt_id);
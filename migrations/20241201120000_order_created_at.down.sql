@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS order_info_created_at_idx;
+ALTER TABLE order_info DROP COLUMN IF EXISTS created_at;
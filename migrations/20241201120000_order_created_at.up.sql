@@ -0,0 +1,12 @@
+-- Момент создания заказа как метка времени. date_created хранится строкой из исходного сообщения
+-- и может содержать любое смещение часового пояса, поэтому список заказов сортируется по created_at.
+-- Заказам, у которых date_created не в формате RFC 3339, присваивается -infinity
+ALTER TABLE order_info ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT '-infinity';
+
+UPDATE order_info SET created_at = date_created::timestamptz
+WHERE date_created ~ '^\d{4}-\d{2}-\d{2}[Tt]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})$';
+
+-- Значение задается при записи заказа
+ALTER TABLE order_info ALTER COLUMN created_at DROP DEFAULT;
+
+CREATE INDEX IF NOT EXISTS order_info_created_at_idx ON order_info (created_at, order_uid);
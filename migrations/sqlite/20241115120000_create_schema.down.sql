@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS item_status_history;
+DROP TABLE IF EXISTS dead_letter;
+DROP TABLE IF EXISTS order_item;
+DROP TABLE IF EXISTS item;
+DROP TABLE IF EXISTS order_info;
+DROP TABLE IF EXISTS payment;
+DROP TABLE IF EXISTS delivery;
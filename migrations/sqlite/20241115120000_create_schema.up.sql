@@ -0,0 +1,81 @@
+-- Схема SQLite повторяет схему PostgreSQL после миграции item_status_history:
+-- BIGSERIAL заменен на INTEGER PRIMARY KEY AUTOINCREMENT, TIMESTAMPTZ - на текст в формате RFC 3339
+CREATE TABLE IF NOT EXISTS delivery (
+    delivery_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(255),
+    phone VARCHAR(20),
+    zip VARCHAR(20),
+    city VARCHAR(255),
+    address VARCHAR(255),
+    region VARCHAR(255),
+    email VARCHAR(255)
+);
+
+CREATE TABLE IF NOT EXISTS payment (
+    "transaction" VARCHAR(255) PRIMARY KEY,
+    request_id VARCHAR(255),
+    currency VARCHAR(10),
+    provider VARCHAR(50),
+    amount INTEGER,
+    payment_dt BIGINT,
+    bank VARCHAR(255),
+    delivery_cost INTEGER,
+    goods_total INTEGER,
+    custom_fee INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS order_info (
+    order_uid VARCHAR(255) PRIMARY KEY,
+    track_number VARCHAR(255),
+    entry VARCHAR(50),
+    delivery_id BIGINT REFERENCES delivery ON DELETE SET NULL,
+    payment_transaction VARCHAR(255) REFERENCES payment ON DELETE SET NULL,
+    locale VARCHAR(10),
+    internal_signature VARCHAR(255),
+    customer_id VARCHAR(255),
+    delivery_service VARCHAR(50),
+    shardkey VARCHAR(10),
+    sm_id BIGINT,
+    date_created VARCHAR(50),
+    oof_shard VARCHAR(10)
+);
+
+CREATE TABLE IF NOT EXISTS item (
+    item_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chrt_id BIGINT,
+    track_number VARCHAR(255),
+    price INTEGER,
+    rid VARCHAR(255),
+    name VARCHAR(255),
+    sale INTEGER,
+    size VARCHAR(10),
+    total_price INTEGER,
+    nm_id BIGINT,
+    brand VARCHAR(255),
+    status INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS order_item (
+    order_uid VARCHAR(255) REFERENCES order_info ON DELETE CASCADE,
+    item_id BIGINT NOT NULL REFERENCES item ON DELETE CASCADE,
+    PRIMARY KEY(order_uid, item_id)
+);
+
+CREATE TABLE IF NOT EXISTS dead_letter (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    payload TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    source VARCHAR(50) NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS item_status_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_uid VARCHAR(255) NOT NULL REFERENCES order_info ON DELETE CASCADE,
+    chrt_id BIGINT NOT NULL,
+    old_status INTEGER,
+    new_status INTEGER NOT NULL,
+    changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS item_status_history_item_idx ON item_status_history (order_uid, chrt_id);
@@ -0,0 +1,17 @@
+-- Момент создания заказа в микросекундах от начала эпохи Unix, как created_at в PostgreSQL.
+-- date_created хранится строкой из исходного сообщения и может содержать любое смещение часового пояса,
+-- поэтому список заказов сортируется по created_at. Заказам, у которых date_created не в формате RFC 3339,
+-- присваивается минимальное значение (как -infinity в PostgreSQL)
+ALTER TABLE order_info ADD COLUMN created_at INTEGER NOT NULL DEFAULT -9223372036854775808;
+
+-- Секунды вычисляются SQLite с учетом смещения, дробная часть (до 6 цифр) берется из строки
+UPDATE order_info SET created_at =
+    CAST(strftime('%s', date_created) AS INTEGER) * 1000000
+    + CASE WHEN substr(date_created, 20, 1) = '.' THEN CAST(substr(
+        substr(date_created, 21, length(date_created) - 20 - CASE WHEN upper(substr(date_created, -1)) = 'Z' THEN 1 ELSE 6 END)
+        || '000000', 1, 6) AS INTEGER)
+    ELSE 0 END
+WHERE date_created GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9][Tt][0-9][0-9]:[0-9][0-9]:[0-9][0-9]*'
+    AND strftime('%s', date_created) IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS order_info_created_at_idx ON order_info (created_at, order_uid);
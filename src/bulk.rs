@@ -0,0 +1,258 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Json, State},
+    response::IntoResponse,
+};
+use futures::StreamExt;
+use log::{info, error}; // Макросы для логирования
+use serde::Serialize;
+use serde_json::{json, value::RawValue, Value};
+
+use crate::db::AddOrderOutcome; // Результат добавления заказа
+use crate::dead_letter; // Хранение отклоненных сообщений
+use crate::error::Error; // Тип ошибки приложения
+use crate::model::Order; // Модель заказа
+use crate::validation; // Проверка бизнес-правил заказов
+use crate::AppState; // Состояние приложения
+
+// Максимальный размер JSON-массива заказов и одной строки NDJSON
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+// Итог обработки одного заказа
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Created, // Заказ добавлен
+    Duplicate, // Заказ с таким UID уже существует
+    Conflict, // Идентификатор оплаты занят другим заказом
+    Invalid, // Заказ не прошел разбор или проверку и сохранен как отклоненное сообщение
+    Failed, // Пакет с заказом не записан из-за ошибки хранилища
+}
+
+// Результат обработки одного заказа
+#[derive(Serialize)]
+struct OrderResult {
+    index: usize, // Порядковый номер заказа в теле запроса
+    order_uid: Option<String>, // UID заказа, если его удалось прочитать
+    status: Status, // Итог обработки
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Value>, // Ошибка в том же виде, что и в ответах API
+}
+
+// Заказ, ожидающий записи в составе пакета
+struct Pending {
+    index: usize, // Порядковый номер заказа в теле запроса
+    payload: Vec<u8>, // Исходный JSON заказа (сохраняется как отклоненное сообщение при ошибке данных)
+}
+
+// Загрузка заказов пакетами: прочитанные заказы накапливаются и записываются
+// одной транзакцией, когда набирается пакет заданного размера
+struct Loader<'a> {
+    state: &'a AppState, // Состояние приложения
+    orders: Vec<Order>, // Заказы текущего пакета
+    pending: Vec<Pending>, // Сведения о заказах текущего пакета
+    results: Vec<OrderResult>, // Результаты обработанных заказов
+    count: usize, // Количество прочитанных заказов
+}
+
+impl<'a> Loader<'a> {
+    fn new(state: &'a AppState) -> Self {
+        Loader {
+            state,
+            orders: Vec::with_capacity(state.bulk_batch_size),
+            pending: Vec::with_capacity(state.bulk_batch_size),
+            results: Vec::new(),
+            count: 0,
+        }
+    }
+
+    // Разбор и проверка очередного заказа, заказ добавляется в текущий пакет
+    async fn push(&mut self, payload: &[u8]) {
+        let index = self.count;
+        self.count += 1;
+
+        // UID читается отдельно, чтобы указать его и в результате для некорректного заказа
+        let value = serde_json::from_slice::<Value>(payload);
+        let order_uid = value.as_ref().ok()
+            .and_then(|value| value.get("order_uid"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let order = value
+            .and_then(serde_json::from_value::<Order>)
+            .map_err(Error::from)
+            .and_then(|order| validation::validate(&order, &self.state.validation).map(|_| order));
+
+        match order {
+            Ok(order) => {
+                self.orders.push(order);
+                self.pending.push(Pending { index, payload: payload.to_vec() });
+                if self.orders.len() >= self.state.bulk_batch_size {
+                    self.flush().await;
+                }
+            }
+            Err(e) => self.reject(index, order_uid, payload, e).await,
+        }
+    }
+
+    // Очередная строка NDJSON, пустые строки пропускаются
+    async fn push_line(&mut self, line: &[u8]) {
+        let line = line.trim_ascii();
+        if !line.is_empty() {
+            self.push(line).await;
+        }
+    }
+
+    // Запись текущего пакета в хранилище. Ошибка записи пакета не прерывает загрузку,
+    // заказы пакета получают статус failed
+    async fn flush(&mut self) {
+        if self.orders.is_empty() {
+            return;
+        }
+        let orders = std::mem::take(&mut self.orders);
+        let pending = std::mem::take(&mut self.pending);
+
+        let outcomes = match self.state.repository.add_orders(&orders).await {
+            Ok(outcomes) => outcomes,
+            Err(e) => {
+                error!("Failed to add batch of {} orders: {:?}", orders.len(), e);
+                let error = e.body();
+                for (order, pending) in orders.into_iter().zip(pending) {
+                    self.result(pending.index, Some(order.order_uid), Status::Failed, Some(error.clone()));
+                }
+                return;
+            }
+        };
+
+        for ((order, pending), outcome) in orders.into_iter().zip(pending).zip(outcomes) {
+            match outcome {
+                Ok(AddOrderOutcome::Created) => {
+                    self.result(pending.index, Some(order.order_uid.clone()), Status::Created, None);
+                    // Сохраняем заказ в кэше
+                    self.state.orders.put(order);
+                }
+                Ok(AddOrderOutcome::Duplicate) => {
+                    self.result(pending.index, Some(order.order_uid), Status::Duplicate, None);
+                }
+                Err(e @ Error::Conflict { .. }) => {
+                    self.result(pending.index, Some(order.order_uid), Status::Conflict, Some(e.body()));
+                }
+                Err(e) => self.reject(pending.index, Some(order.order_uid), &pending.payload, e).await,
+            }
+        }
+    }
+
+    // Некорректный заказ сохраняется как отклоненное сообщение
+    async fn reject(&mut self, index: usize, order_uid: Option<String>, payload: &[u8], e: Error) {
+        dead_letter::store(self.state, payload, &e, "bulk").await;
+        self.result(index, order_uid, Status::Invalid, Some(e.body()));
+    }
+
+    fn result(&mut self, index: usize, order_uid: Option<String>, status: Status, error: Option<Value>) {
+        self.results.push(OrderResult { index, order_uid, status, error });
+    }
+
+    // Ответ со сводкой и результатами в порядке заказов в теле запроса
+    fn finish(mut self) -> Value {
+        self.results.sort_by_key(|result| result.index);
+
+        let count = |status| self.results.iter().filter(|result| result.status == status).count();
+        let summary = json!({
+            "total": self.results.len(),
+            "created": count(Status::Created),
+            "duplicate": count(Status::Duplicate),
+            "conflict": count(Status::Conflict),
+            "invalid": count(Status::Invalid),
+            "failed": count(Status::Failed),
+        });
+        info!("Bulk load finished: {}", summary);
+
+        json!({
+            "summary": summary,
+            "results": self.results,
+        })
+    }
+}
+
+// Ошибка слишком большого тела запроса
+fn too_large() -> Error {
+    Error::Validation {
+        message: "Request body is too large".to_string(),
+        details: json!({ "limit": MAX_BODY_SIZE }),
+    }
+}
+
+// Асинхронная функция для массовой загрузки заказов. Тело запроса - JSON-массив заказов
+// или NDJSON (по заказу в строке), формат определяется по первому символу тела.
+// JSON-массив читается целиком, NDJSON обрабатывается по мере получения строк.
+// Повторы UID не считаются ошибкой и возвращаются со статусом duplicate независимо от режима приема заказов
+pub async fn load(
+    State(state): State<AppState>, // Извлекаем состояние приложения
+    body: Body, // Тело запроса читается потоком
+) -> Result<impl IntoResponse, Error> {
+    let mut stream = body.into_data_stream();
+    let mut loader = Loader::new(&state);
+    let mut buffer = Vec::new();
+
+    // Читаем тело до первого непробельного символа, чтобы определить формат
+    let first = loop {
+        if let Some(&first) = buffer.iter().find(|byte: &&u8| !byte.is_ascii_whitespace()) {
+            break Some(first);
+        }
+        match stream.next().await {
+            Some(chunk) => buffer.extend_from_slice(&chunk.map_err(|e| Error::Backend(e.into()))?),
+            None => break None,
+        }
+    };
+
+    if first == Some(b'[') {
+        // JSON-массив: читаем тело целиком и разбираем каждый заказ по отдельности
+        while let Some(chunk) = stream.next().await {
+            buffer.extend_from_slice(&chunk.map_err(|e| Error::Backend(e.into()))?);
+            if buffer.len() > MAX_BODY_SIZE {
+                return Err(too_large());
+            }
+        }
+
+        let entries: Vec<&RawValue> = serde_json::from_slice(&buffer).map_err(|e| Error::Validation {
+            message: "Invalid JSON array of orders".to_string(),
+            details: json!({ "reason": e.to_string() }),
+        })?;
+        info!("Bulk load of {} orders", entries.len());
+
+        for entry in entries {
+            loader.push(entry.get().as_bytes()).await;
+        }
+    } else {
+        // NDJSON: обрабатываем строки по мере получения, в буфере остается только незавершенная строка
+        info!("Bulk load of NDJSON stream");
+
+        let mut line = Vec::new();
+        let mut chunk = Bytes::from(buffer);
+        loop {
+            let mut rest = &chunk[..];
+            while let Some(end) = rest.iter().position(|&byte| byte == b'\n') {
+                line.extend_from_slice(&rest[..end]);
+                loader.push_line(&line).await;
+                line.clear();
+                rest = &rest[end + 1..];
+            }
+            line.extend_from_slice(rest);
+            if line.len() > MAX_BODY_SIZE {
+                return Err(too_large());
+            }
+
+            chunk = match stream.next().await {
+                Some(chunk) => chunk.map_err(|e| Error::Backend(e.into()))?,
+                None => break,
+            };
+        }
+        // Последняя строка может не заканчиваться переводом строки
+        loader.push_line(&line).await;
+    }
+
+    // Записываем неполный последний пакет
+    loader.flush().await;
+
+    Ok(Json(loader.finish()))
+}
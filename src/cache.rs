@@ -0,0 +1,159 @@
+use std::collections::hash_map::RandomState; // Хэшер для распределения ключей по сегментам
+use std::hash::BuildHasher;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::info; // Макрос для логирования информации
+use lru::LruCache;
+
+use crate::repository::OrderRepository; // Трейт хранилища заказов
+use crate::error::Error; // Тип ошибки приложения
+use crate::metrics; // Метрики обращений к кэшу
+use crate::model::Order; // Модель заказа
+
+// Максимальное количество сегментов кэша
+const MAX_SHARDS: usize = 16;
+
+// Количество заказов, загружаемых за один запрос при прогреве кэша
+const WARM_UP_BATCH_SIZE: usize = 500;
+
+// Сегментированный LRU-кэш заказов.
+// Каждый сегмент защищен своей блокировкой, поэтому обращения к разным сегментам
+// выполняются параллельно, а блокировка удерживается только на время операции над LruCache.
+// Суммарная емкость сегментов равна размеру кэша, переданному при создании.
+pub struct OrderCache {
+    shards: Vec<Mutex<LruCache<String, Arc<Order>>>>, // Сегменты кэша
+    hasher: RandomState, // Хэшер для выбора сегмента по ключу
+    initialized: AtomicBool, // Признак завершения инициализации (прогрева) кэша
+}
+
+impl OrderCache {
+    // Создание кэша заданного размера
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        let shards_count = capacity.get().min(MAX_SHARDS);
+        // Распределяем емкость по сегментам так, чтобы сумма была равна capacity
+        let shards = (0..shards_count)
+            .map(|i| {
+                let shard_capacity = capacity.get() / shards_count + usize::from(i < capacity.get() % shards_count);
+                Mutex::new(LruCache::new(NonZeroUsize::new(shard_capacity).unwrap()))
+            })
+            .collect();
+
+        OrderCache {
+            shards,
+            hasher: RandomState::new(),
+            initialized: AtomicBool::new(false),
+        }
+    }
+
+    // Отметка о завершении инициализации кэша
+    pub fn mark_initialized(&self) {
+        self.initialized.store(true, Ordering::Release);
+    }
+
+    // Завершена ли инициализация кэша
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Acquire)
+    }
+
+    // Количество заказов в кэше
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+    }
+
+    // Пуст ли кэш
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.lock().unwrap().is_empty())
+    }
+
+    // Суммарная емкость кэша
+    pub fn capacity(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().cap().get()).sum()
+    }
+
+    // Номер сегмента, в котором хранится заказ с данным UID
+    fn shard_index(&self, order_uid: &str) -> usize {
+        self.hasher.hash_one(order_uid) as usize % self.shards.len()
+    }
+
+    // Сегмент, в котором хранится заказ с данным UID
+    fn shard(&self, order_uid: &str) -> &Mutex<LruCache<String, Arc<Order>>> {
+        &self.shards[self.shard_index(order_uid)]
+    }
+
+    // Получение заказа из кэша (обновляет позицию заказа в LRU-очереди сегмента)
+    pub fn get(&self, order_uid: &str) -> Option<Arc<Order>> {
+        let order = self.shard(order_uid).lock().unwrap().get(order_uid).cloned();
+        match order {
+            Some(_) => metrics::CACHE_HITS.inc(),
+            None => metrics::CACHE_MISSES.inc(),
+        }
+        order
+    }
+
+    // Сохранение заказа в кэше
+    pub fn put(&self, order: Order) {
+        let order_uid = order.order_uid.clone();
+        let replaced = self.shard(&order_uid)
+            .lock()
+            .unwrap()
+            .push(order_uid.clone(), Arc::new(order));
+        // push возвращает либо прежнее значение того же ключа, либо вытесненный заказ
+        if matches!(replaced, Some((key, _)) if key != order_uid) {
+            metrics::CACHE_EVICTIONS.inc();
+        }
+    }
+
+    // Удаление заказа из кэша
+    pub fn remove(&self, order_uid: &str) {
+        self.shard(order_uid).lock().unwrap().pop(order_uid);
+    }
+
+    // Заполнение кэша без вытеснения: заказы (от самого нового к самому старому) помещаются
+    // в свои сегменты, пока в сегменте есть свободное место, остальные пропускаются.
+    // Возвращает количество заказов, помещенных в кэш
+    pub fn fill(&self, orders: Vec<Order>) -> usize {
+        let mut shard_orders: Vec<Vec<Order>> = self.shards.iter().map(|_| Vec::new()).collect();
+        for order in orders {
+            shard_orders[self.shard_index(&order.order_uid)].push(order);
+        }
+
+        let mut stored = 0;
+        for (shard, orders) in self.shards.iter().zip(shard_orders) {
+            let mut shard = shard.lock().unwrap();
+            let room = shard.cap().get() - shard.len();
+            let orders: Vec<Order> = orders.into_iter().take(room).collect();
+            stored += orders.len();
+            // Более новые заказы кладутся в сегмент последними, чтобы вытесняться позже
+            for order in orders.into_iter().rev() {
+                shard.push(order.order_uid.clone(), Arc::new(order));
+            }
+        }
+        stored
+    }
+}
+
+// Прогрев кэша: загрузка count самых новых заказов (по моменту создания).
+// Сначала загружаются UID заказов, затем сами заказы пакетами по списку UID.
+// Емкость кэша делится между сегментами, а заказы распределяются по ним неравномерно, поэтому
+// заказы, не поместившиеся в свой сегмент, пропускаются, а не вытесняют уже загруженные.
+// Возвращает количество заказов, помещенных в кэш
+pub async fn warm_up(cache: &OrderCache, repository: &dyn OrderRepository, count: usize) -> Result<usize, Error> {
+    let count = count.min(cache.capacity());
+    info!("Warming up order cache with {} most recent orders", count);
+
+    let order_uids = repository.recent_order_uids(count as i64).await?;
+    let mut orders = Vec::with_capacity(order_uids.len());
+    for batch in order_uids.chunks(WARM_UP_BATCH_SIZE) {
+        // Заказы, удаленные после получения списка UID, пропускаются
+        orders.extend(repository.get_orders(batch).await?);
+        info!("Cache warm-up progress: {}/{} orders", orders.len(), order_uids.len());
+    }
+
+    let fetched = orders.len();
+    let loaded = cache.fill(orders);
+
+    info!("Cache warm-up finished: {} of {} orders loaded", loaded, fetched);
+    Ok(loaded)
+}
@@ -0,0 +1,187 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use log::{info, warn, error}; // Макросы для логирования
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::cli::IngestMode; // Режим обработки повторных заказов
+use crate::error::Error; // Тип ошибки приложения
+use crate::model::Order; // Модель заказа
+use crate::AppState; // Состояние приложения
+
+// Пауза перед повторной попыткой получить сообщение после ошибки брокера
+const RECEIVE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// Задержка повторной доставки сообщения после ошибки базы данных,
+// удваивается при каждой следующей ошибке подряд до REDELIVERY_MAX_DELAY
+pub const REDELIVERY_INITIAL_DELAY: Duration = Duration::from_secs(1);
+pub const REDELIVERY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+// Сообщение, полученное из брокера
+pub trait Message: Send {
+    // Тело сообщения (заказ в формате JSON)
+    fn payload(&self) -> &[u8];
+
+    // Подтверждение обработки: сообщение больше не будет доставлено
+    fn ack(self) -> impl Future<Output = Result<(), Error>> + Send;
+
+    // Отказ от обработки: сообщение будет доставлено повторно не раньше, чем через delay
+    fn nack(self, delay: Duration) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+// Источник сообщений с заказами (подписка на subject/topic брокера)
+pub trait Consumer: Send + 'static {
+    type Message: Message;
+
+    // Название источника (сохраняется вместе с отклоненными сообщениями)
+    const SOURCE: &'static str;
+
+    // Получение следующего сообщения, None означает, что источник закрыт
+    fn next(&mut self) -> impl Future<Output = Result<Option<Self::Message>, Error>> + Send;
+}
+
+// Цикл обработки сообщений: каждый заказ проходит через хранилище и кэш,
+// сообщение подтверждается только после сохранения заказа.
+// После ошибки базы данных новые сообщения не принимаются до истечения задержки повторной доставки.
+// После сигнала завершения новые сообщения не принимаются, полученное сообщение обрабатывается до конца
+pub async fn run<C: Consumer>(mut consumer: C, state: AppState, mut shutdown: watch::Receiver<Option<Instant>>) {
+    info!("Starting order consumer");
+    let mut redelivery_delay = REDELIVERY_INITIAL_DELAY;
+
+    loop {
+        let received = tokio::select! {
+            _ = shutdown.wait_for(Option::is_some) => {
+                info!("Order consumer stopped: shutting down");
+                break;
+            }
+            received = consumer.next() => received,
+        };
+
+        let message = match received {
+            Ok(Some(message)) => message,
+            Ok(None) => {
+                info!("Order consumer stopped: source closed");
+                break;
+            }
+            Err(e) => {
+                error!("Failed to receive order message: {}", e);
+                tokio::time::sleep(RECEIVE_RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        if !handle_message(message, &state, C::SOURCE, redelivery_delay).await {
+            redelivery_delay = REDELIVERY_INITIAL_DELAY;
+            continue;
+        }
+
+        // База данных недоступна: ждем, пока сообщение не будет доставлено повторно,
+        // чтобы не получать и не отклонять сообщения в цикле, нагружая базу данных
+        tokio::select! {
+            _ = shutdown.wait_for(Option::is_some) => {
+                info!("Order consumer stopped: shutting down");
+                break;
+            }
+            _ = tokio::time::sleep(redelivery_delay) => {}
+        }
+        redelivery_delay = (redelivery_delay * 2).min(REDELIVERY_MAX_DELAY);
+    }
+}
+
+// Обработка одного сообщения. Возвращает true, если из-за ошибки базы данных
+// запрошена повторная доставка сообщения через redelivery_delay
+async fn handle_message<M: Message>(message: M, state: &AppState, source: &str, redelivery_delay: Duration) -> bool {
+    // Брокер может доставить сообщение повторно, поэтому повторы всегда принимаются без изменений
+    let result = match serde_json::from_slice::<Order>(message.payload()) {
+        Ok(order) => crate::ingest_order(state, order, IngestMode::Idempotent).await.map(|order| {
+            info!("Order {:?} consumed from queue", order.order_uid);
+        }),
+        Err(e) => Err(e.into()),
+    };
+
+    let redelivery = matches!(result, Err(Error::Backend(_)));
+    let acknowledged = match result {
+        Ok(()) => message.ack().await,
+        // Ошибка базы данных может быть временной: просим брокер доставить сообщение повторно
+        Err(e @ Error::Backend(_)) => {
+            warn!("Failed to consume order message, requesting redelivery in {:?}: {}", redelivery_delay, e);
+            message.nack(redelivery_delay).await
+        }
+        // Некорректное сообщение не станет корректным при повторной доставке,
+        // поэтому оно сохраняется для ручной повторной обработки и подтверждается
+        Err(e) => {
+            error!("Rejected order message: {}", e);
+            crate::dead_letter::store(state, message.payload(), &e, source).await;
+            message.ack().await
+        }
+    };
+
+    if let Err(e) = acknowledged {
+        error!("Failed to acknowledge order message: {}", e);
+    }
+    redelivery
+}
+
+// Результат обработки сообщения внутрипроцессного канала
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acknowledgement {
+    Ack, // Сообщение подтверждено
+    Nack(Duration), // Запрошена повторная доставка через заданное время
+}
+
+// Сообщение внутрипроцессного канала
+pub struct ChannelMessage {
+    payload: Vec<u8>, // Тело сообщения
+    acknowledgement: oneshot::Sender<Acknowledgement>, // Результат обработки сообщения
+}
+
+impl Message for ChannelMessage {
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    async fn ack(self) -> Result<(), Error> {
+        let _ = self.acknowledgement.send(Acknowledgement::Ack);
+        Ok(())
+    }
+
+    async fn nack(self, delay: Duration) -> Result<(), Error> {
+        let _ = self.acknowledgement.send(Acknowledgement::Nack(delay));
+        Ok(())
+    }
+}
+
+// Внутрипроцессный источник сообщений, заменяющий брокер в тестах
+pub struct ChannelConsumer {
+    receiver: mpsc::Receiver<ChannelMessage>,
+}
+
+impl Consumer for ChannelConsumer {
+    type Message = ChannelMessage;
+
+    const SOURCE: &'static str = "channel";
+
+    async fn next(&mut self) -> Result<Option<ChannelMessage>, Error> {
+        Ok(self.receiver.recv().await)
+    }
+}
+
+// Отправитель сообщений во внутрипроцессный канал
+pub struct ChannelPublisher {
+    sender: mpsc::Sender<ChannelMessage>,
+}
+
+impl ChannelPublisher {
+    // Публикация сообщения, результат подтверждения приходит в возвращаемый канал
+    pub async fn publish(&self, payload: Vec<u8>) -> oneshot::Receiver<Acknowledgement> {
+        let (acknowledgement, result) = oneshot::channel();
+        let _ = self.sender.send(ChannelMessage { payload, acknowledgement }).await;
+        result
+    }
+}
+
+// Создание внутрипроцессного канала сообщений
+pub fn channel(buffer: usize) -> (ChannelPublisher, ChannelConsumer) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    (ChannelPublisher { sender }, ChannelConsumer { receiver })
+}
@@ -1,31 +1,288 @@
-use std::error::Error; // Импортируем тип Error для обработки ошибок
-use tokio_postgres::Client; // Импортируем клиент для работы с PostgreSQL
-use crate::model::{Order, Delivery, Payment, Item}; // Импортируем модели данных
+use std::collections::{HashMap, HashSet}; // Для группировки товаров по заказам и поиска повторов
+use std::sync::Mutex; // Защита кэша подготовленных запросов
+use chrono::{DateTime, SecondsFormat, Utc}; // Момент создания заказа для сортировки и курсора
+use tokio_postgres::{types::{Json, ToSql}, GenericClient, Statement}; // Импортируем общий трейт для клиента и транзакции
+use crate::model::{Order, Delivery, Payment, Item, ItemStatus, ItemStatusChange, DeadLetter}; // Импортируем модели данных
+use crate::error::Error; // Импортируем тип ошибки приложения
+use crate::metrics; // Замер времени выполнения функций модуля
+use serde_json::json; // Для формирования сведений об ошибке
 use log::info; // Импортируем макрос для логирования информации
 
+// Результат добавления заказа
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddOrderOutcome {
+    Created, // Заказ добавлен
+    Duplicate, // Заказ с таким UID уже был добавлен ранее, данные не изменялись
+}
+
+// Кэш подготовленных запросов одного соединения: запрос разбирается сервером при первом выполнении,
+// повторные выполнения передают только параметры. Подготовленные запросы принадлежат соединению,
+// поэтому у каждого соединения пула свой кэш
+#[derive(Default)]
+pub struct StatementCache {
+    statements: Mutex<HashMap<&'static str, Statement>>, // Подготовленные запросы по тексту запроса
+}
+
+impl StatementCache {
+    // Получение подготовленного запроса, при первом обращении запрос подготавливается на сервере
+    pub async fn prepare(&self, query: &'static str, client: &impl GenericClient) -> Result<Statement, Error> {
+        if let Some(statement) = self.statements.lock().unwrap().get(query) {
+            return Ok(statement.clone());
+        }
+
+        let statement = client.prepare(query).await?;
+        self.statements.lock().unwrap().insert(query, statement.clone());
+        Ok(statement)
+    }
+}
+
 // Асинхронная функция для добавления заказа в базу данных
-pub async fn add_order(order: &Order, client: &Client) -> Result<(), Box<dyn Error>> {
+// Все вставки выполняются в одной транзакции: при любой ошибке изменения откатываются целиком.
+// Принимает как Client, так и Transaction - во втором случае заказ пишется в точку сохранения (SAVEPOINT)
+// внешней транзакции, что позволяет вызывающей стороне объединять несколько заказов в один коммит.
+// Повторная запись заказа с тем же UID ничего не меняет и возвращает AddOrderOutcome::Duplicate.
+// Количество запросов не зависит от количества товаров, запросы берутся из кэша подготовленных запросов соединения
+pub async fn add_order<C: GenericClient>(order: &Order, client: &mut C, statements: &StatementCache) -> Result<AddOrderOutcome, Error> {
+    let _timer = metrics::db_timer("add_order");
     info!("Adding order with ID: {:?}", order.order_uid); // Логируем добавление заказа
 
+    // Открываем транзакцию (при drop без commit она будет откачена)
+    let transaction = client.transaction().await?;
+
+    // Блокируем UID заказа до конца транзакции, чтобы параллельные повторы одного заказа
+    // выполнялись последовательно и не приводили к нарушению первичных ключей
+    lock_order(&order.order_uid, &transaction, statements).await?;
+
+    // Если заказ уже существует, повтор считается успешным и ничего не меняет
+    if order_exists(&order.order_uid, &transaction, statements).await? {
+        info!("Order with ID {:?} already exists, skipping", order.order_uid);
+        return Ok(AddOrderOutcome::Duplicate);
+    }
+
     // Вставляем информацию о доставке и получаем ID доставки
-    let delivery_id = insert_delivery(&order.delivery, client).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+    let delivery_id = insert_delivery(&order.delivery, &transaction, statements).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
     // Вставляем информацию о платеже
-    insert_payment(&order.payment, client).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+    insert_payment(&order.payment, &transaction, statements).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
     // Вставляем информацию о заказе
-    insert_order(order, client, delivery_id).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+    insert_order(order, &transaction, delivery_id, statements).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
 
-    // Вставляем каждый элемент заказа
-    for item in &order.items {
-        insert_item(item, client).await?; // Вставляем элемент
-        insert_order_item(order, item, client).await?; // Связываем элемент с заказом
-    }
+    // Вставляем все товары заказа и связываем их с заказом одним запросом
+    insert_items(&[order], &transaction, statements).await?;
+    // Записываем начальные статусы товаров в историю
+    insert_item_status_history(&initial_item_statuses(&[order])?, &transaction, statements).await?;
+
+    // Фиксируем транзакцию
+    transaction.commit().await?;
 
     info!("Successfully added order with ID: {:?}", order.order_uid); // Логируем успешное добавление заказа
-    Ok(()) // Возвращаем успешный результат
+    Ok(AddOrderOutcome::Created) // Возвращаем успешный результат
+}
+
+// Асинхронная функция для добавления пакета заказов. Результат возвращается для каждого заказа
+// в порядке пакета: Created, Duplicate (заказ уже сохранен или повторяется в пакете) или ошибка заказа
+// (например, конфликт идентификатора оплаты). Ошибка всей функции означает, что пакет не записан.
+// Все заказы пакета записываются одной транзакцией многострочными INSERT (по одному на таблицу),
+// если такая запись не удалась из-за данных одного из заказов, пакет записывается по одному заказу
+pub async fn add_orders<C: GenericClient>(orders: &[Order], client: &mut C, statements: &StatementCache) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+    let _timer = metrics::db_timer("add_orders");
+    info!("Adding batch of {} orders", orders.len());
+
+    match add_orders_batch(orders, client, statements).await {
+        Err(Error::Conflict { .. } | Error::Validation { .. }) => {
+            info!("Batch insert failed, adding orders one by one");
+            add_orders_one_by_one(orders, client, statements).await
+        }
+        result => result,
+    }
+}
+
+// Асинхронная функция для записи пакета заказов многострочными INSERT в одной транзакции
+async fn add_orders_batch<C: GenericClient>(orders: &[Order], client: &mut C, statements: &StatementCache) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+    let transaction = client.transaction().await?;
+
+    // Блокируем UID всех заказов пакета (в одном порядке, чтобы параллельные пакеты не блокировали друг друга)
+    let mut order_uids: Vec<&String> = orders.iter().map(|order| &order.order_uid).collect();
+    order_uids.sort();
+    order_uids.dedup();
+    transaction.execute("SELECT pg_advisory_xact_lock(hashtext(uid)) FROM unnest($1::text[]) AS uid", &[&order_uids]).await?;
+
+    // Уже сохраненные заказы и занятые идентификаторы оплаты
+    let existing_orders: HashSet<String> = transaction
+        .query("SELECT order_uid FROM order_info WHERE order_uid = ANY($1)", &[&order_uids]).await?
+        .iter().map(|row| row.get(0)).collect();
+    let transactions: Vec<&String> = orders.iter().map(|order| &order.payment.transaction).collect();
+    let existing_payments: HashSet<String> = transaction
+        .query("SELECT transaction FROM payment WHERE transaction = ANY($1)", &[&transactions]).await?
+        .iter().map(|row| row.get(0)).collect();
+
+    // Отбираем новые заказы, повторы UID и идентификаторов оплаты внутри пакета проверяются так же, как с базой данных
+    let mut results = Vec::with_capacity(orders.len());
+    let mut new_orders: Vec<&Order> = Vec::new();
+    let mut new_order_uids: HashSet<&String> = HashSet::new();
+    let mut new_payments: HashSet<&String> = HashSet::new();
+    for order in orders {
+        if existing_orders.contains(&order.order_uid) || new_order_uids.contains(&order.order_uid) {
+            results.push(Ok(AddOrderOutcome::Duplicate));
+        } else if existing_payments.contains(&order.payment.transaction) || new_payments.contains(&order.payment.transaction) {
+            results.push(Err(Error::payment_conflict(&order.payment.transaction)));
+        } else {
+            new_order_uids.insert(&order.order_uid);
+            new_payments.insert(&order.payment.transaction);
+            new_orders.push(order);
+            results.push(Ok(AddOrderOutcome::Created));
+        }
+    }
+
+    if !new_orders.is_empty() {
+        insert_orders_batch(&new_orders, &transaction, statements).await?;
+    }
+
+    // Фиксируем транзакцию
+    transaction.commit().await?;
+
+    info!("Successfully added batch of {} orders, {} new", orders.len(), new_orders.len());
+    Ok(results)
+}
+
+// Асинхронная функция для записи пакета заказов по одному: каждый заказ пишется в свою точку сохранения,
+// поэтому ошибка одного заказа не отменяет запись остальных
+async fn add_orders_one_by_one<C: GenericClient>(orders: &[Order], client: &mut C, statements: &StatementCache) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+    let mut transaction = client.transaction().await?;
+
+    let mut results = Vec::with_capacity(orders.len());
+    for order in orders {
+        match add_order(order, &mut transaction, statements).await {
+            Err(e @ Error::Backend(_)) => return Err(e),
+            result => results.push(result),
+        }
+    }
+
+    // Фиксируем транзакцию
+    transaction.commit().await?;
+    Ok(results)
+}
+
+// Асинхронная функция для вставки новых заказов многострочными INSERT: значения каждого столбца
+// передаются массивом и разворачиваются через unnest, поэтому на таблицу выполняется один запрос
+async fn insert_orders_batch(orders: &[&Order], client: &impl GenericClient, statements: &StatementCache) -> Result<(), Error> {
+    // Заранее получаем ID доставок из последовательности, чтобы связать их с заказами
+    let delivery_ids: Vec<i64> = client.query(
+        "SELECT nextval(pg_get_serial_sequence('delivery', 'delivery_id')) FROM generate_series(1, $1)",
+        &[&(orders.len() as i32)],
+    ).await?
+        .iter().map(|row| row.get(0)).collect();
+
+    let deliveries: Vec<&Delivery> = orders.iter().map(|order| &order.delivery).collect();
+    client.execute(r#"
+        INSERT INTO delivery (delivery_id, name, phone, zip, city, address, region, email)
+        SELECT * FROM unnest($1::bigint[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[], $8::text[])
+    "#, &[
+        &delivery_ids,
+        &column(&deliveries, |delivery| &delivery.name),
+        &column(&deliveries, |delivery| &delivery.phone),
+        &column(&deliveries, |delivery| &delivery.zip),
+        &column(&deliveries, |delivery| &delivery.city),
+        &column(&deliveries, |delivery| &delivery.address),
+        &column(&deliveries, |delivery| &delivery.region),
+        &column(&deliveries, |delivery| &delivery.email),
+    ]).await?;
+
+    let payments: Vec<&Payment> = orders.iter().map(|order| &order.payment).collect();
+    client.execute(r#"
+        INSERT INTO payment (
+            transaction, request_id, currency, provider, amount,
+            payment_dt, bank, delivery_cost, goods_total, custom_fee
+        )
+        SELECT * FROM unnest(
+            $1::text[], $2::text[], $3::text[], $4::text[], $5::int[],
+            $6::bigint[], $7::text[], $8::int[], $9::int[], $10::int[]
+        )
+    "#, &[
+        &column(&payments, |payment| &payment.transaction),
+        &column(&payments, |payment| &payment.request_id),
+        &column(&payments, |payment| &payment.currency),
+        &column(&payments, |payment| &payment.provider),
+        &column(&payments, |payment| &payment.amount),
+        &column(&payments, |payment| &payment.payment_dt),
+        &column(&payments, |payment| &payment.bank),
+        &column(&payments, |payment| &payment.delivery_cost),
+        &column(&payments, |payment| &payment.goods_total),
+        &column(&payments, |payment| &payment.custom_fee),
+    ]).await?;
+
+    client.execute(r#"
+        INSERT INTO order_info (
+            order_uid, track_number, entry, delivery_id, payment_transaction, locale,
+            internal_signature, customer_id, delivery_service, shardkey, sm_id, date_created, oof_shard, created_at
+        )
+        SELECT
+            order_uid, track_number, entry, delivery_id, payment_transaction, locale,
+            internal_signature, customer_id, delivery_service, shardkey, sm_id, date_created, oof_shard,
+            COALESCE(created_at::timestamptz, '-infinity')
+        FROM unnest(
+            $1::text[], $2::text[], $3::text[], $4::bigint[], $5::text[], $6::text[],
+            $7::text[], $8::text[], $9::text[], $10::text[], $11::bigint[], $12::text[], $13::text[], $14::text[]
+        ) AS o(
+            order_uid, track_number, entry, delivery_id, payment_transaction, locale,
+            internal_signature, customer_id, delivery_service, shardkey, sm_id, date_created, oof_shard, created_at
+        )
+    "#, &[
+        &column(orders, |order| &order.order_uid),
+        &column(orders, |order| &order.track_number),
+        &column(orders, |order| &order.entry),
+        &delivery_ids,
+        &column(orders, |order| &order.payment.transaction),
+        &column(orders, |order| &order.locale),
+        &column(orders, |order| &order.internal_signature),
+        &column(orders, |order| &order.customer_id),
+        &column(orders, |order| &order.delivery_service),
+        &column(orders, |order| &order.shardkey),
+        &column(orders, |order| &order.sm_id),
+        &column(orders, |order| &order.date_created),
+        &column(orders, |order| &order.oof_shard),
+        &orders.iter().map(|order| created_at(order)).collect::<Vec<_>>(),
+    ]).await?;
+
+    insert_items(orders, client, statements).await?;
+    insert_item_status_history(&initial_item_statuses(orders)?, client, statements).await?;
+
+    Ok(())
+}
+
+// Значения одного поля всех записей пакета (параметр-массив для unnest)
+fn column<'a, T, V: ?Sized>(rows: &[&'a T], field: impl Fn(&'a T) -> &'a V) -> Vec<&'a V> {
+    rows.iter().map(|row| field(row)).collect()
+}
+
+// Значение столбца created_at: момент создания заказа в формате RFC 3339 (NULL, если date_created не удалось разобрать,
+// в запросе заменяется на -infinity)
+fn created_at(order: &Order) -> Option<String> {
+    order.created_at().map(timestamp)
+}
+
+// Метка времени в формате RFC 3339 с микросекундами, приводится к timestamptz без потери точности
+pub(crate) fn timestamp(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+// Асинхронная функция для блокировки UID заказа до конца текущей транзакции.
+// Добавление, изменение и удаление одного заказа выполняются последовательно
+pub async fn lock_order(order_uid: &String, client: &impl GenericClient, statements: &StatementCache) -> Result<(), Error> {
+    let _timer = metrics::db_timer("lock_order");
+    let statement = statements.prepare("SELECT pg_advisory_xact_lock(hashtext($1))", client).await?;
+    client.execute(&statement, &[order_uid]).await?;
+    Ok(())
+}
+
+// Асинхронная функция для проверки существования заказа
+async fn order_exists(order_uid: &String, client: &impl GenericClient, statements: &StatementCache) -> Result<bool, Error> {
+    let statement = statements.prepare("SELECT 1 FROM order_info WHERE order_uid = $1", client).await?;
+    let row = client.query_opt(&statement, &[order_uid]).await?;
+    Ok(row.is_some())
 }
 
 // Асинхронная функция для вставки информации о доставке
-async fn insert_delivery(delivery: &Delivery, client: &Client) -> Result<i64, Box<dyn Error>> {
+async fn insert_delivery(delivery: &Delivery, client: &impl GenericClient, statements: &StatementCache) -> Result<i64, Error> {
     info!("Adding delivery"); // Логируем добавление доставки
 
     // SQL-запрос для вставки информации о доставке
@@ -42,7 +299,8 @@ async fn insert_delivery(delivery: &Delivery, client: &Client) -> Result<i64, Bo
         RETURNING delivery_id
     "#;
     // Выполняем запрос и получаем ID доставки
-    let row = client.query_one(query, &[
+    let statement = statements.prepare(query, client).await?;
+    let row = client.query_one(&statement, &[
         &delivery.name, 
         &delivery.phone, 
         &delivery.zip, 
@@ -60,7 +318,7 @@ async fn insert_delivery(delivery: &Delivery, client: &Client) -> Result<i64, Bo
 }
 
 // Асинхронная функция для вставки информации о платеже
-async fn insert_payment(payment: &Payment, client: &Client) -> Result<(), Box<dyn Error>> {
+async fn insert_payment(payment: &Payment, client: &impl GenericClient, statements: &StatementCache) -> Result<(), Error> {
     info!("Adding payment with ID: {:?}", payment.transaction); // Логируем добавление платежа
 
     // SQL-запрос для вставки информации о платеже
@@ -79,7 +337,8 @@ async fn insert_payment(payment: &Payment, client: &Client) -> Result<(), Box<dy
         ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
     "#;
     // Выполняем запрос для вставки информации о платеже
-    client.execute(query, &[
+    let statement = statements.prepare(query, client).await?;
+    client.execute(&statement, &[
         &payment.transaction,
         &payment.request_id,
         &payment.currency,
@@ -98,7 +357,7 @@ async fn insert_payment(payment: &Payment, client: &Client) -> Result<(), Box<dy
 }
 
 // Асинхронная функция для вставки информации о заказе в базу данных
-async fn insert_order(order: &Order, client: &Client, delivery_id: i64) -> Result<(), Box<dyn Error>> {
+async fn insert_order(order: &Order, client: &impl GenericClient, delivery_id: i64, statements: &StatementCache) -> Result<(), Error> {
     // Логируем информацию о добавляемом заказе
     info!("Adding order info with ID: {:?}", order.order_uid);
 
@@ -117,12 +376,14 @@ async fn insert_order(order: &Order, client: &Client, delivery_id: i64) -> Resul
             shardkey,
             sm_id,
             date_created,
-            oof_shard
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            oof_shard,
+            created_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, COALESCE(CAST($14::text AS timestamptz), '-infinity'))
     "#;
 
     // Выполняем запрос с передачей параметров
-    client.execute(query, &[
+    let statement = statements.prepare(query, client).await?;
+    client.execute(&statement, &[
         &order.order_uid,
         &order.track_number,
         &order.entry,
@@ -136,6 +397,7 @@ async fn insert_order(order: &Order, client: &Client, delivery_id: i64) -> Resul
         &order.sm_id,
         &order.date_created,
         &order.oof_shard,
+        &created_at(order),
     ]).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
 
     // Логируем успешное добавление заказа
@@ -143,197 +405,726 @@ async fn insert_order(order: &Order, client: &Client, delivery_id: i64) -> Resul
     Ok(())
 }
 
-// Асинхронная функция для вставки информации о товаре в базу данных
-async fn insert_item(item: &Item, client: &Client) -> Result<(), Box<dyn Error>> {
-    // Логируем информацию о добавляемом товаре
-    info!("Adding item with ID: {:?}", item.chrt_id);
+// Асинхронная функция для вставки товаров заказов и их связей с заказами одним запросом.
+// Значения каждого столбца передаются массивом и разворачиваются через unnest, ID товаров берутся
+// из последовательности в подзапросе, результат которого используется обеими вставками
+async fn insert_items(orders: &[&Order], client: &impl GenericClient, statements: &StatementCache) -> Result<(), Error> {
+    // Товары всех заказов с UID заказа, к которому они относятся
+    let (order_uids, items): (Vec<&String>, Vec<&Item>) = orders.iter()
+        .flat_map(|order| order.items.iter().map(move |item| (&order.order_uid, item)))
+        .unzip();
+    if items.is_empty() {
+        return Ok(());
+    }
+    info!("Adding {} items", items.len());
+
+    // Подзапрос new_item материализуется (nextval - изменчивая функция), поэтому обе вставки получают одни и те же ID,
+    // ID выдаются в порядке товаров в массиве, что сохраняет порядок товаров в заказе
+    let statement = statements.prepare(r#"
+        WITH new_item AS (
+            SELECT nextval(pg_get_serial_sequence('item', 'item_id')) AS item_id, *
+            FROM unnest(
+                $1::text[], $2::bigint[], $3::text[], $4::int[], $5::text[], $6::text[],
+                $7::int[], $8::text[], $9::int[], $10::bigint[], $11::text[], $12::int[]
+            ) AS t(order_uid, chrt_id, track_number, price, rid, name, sale, size, total_price, nm_id, brand, status)
+        ), inserted_item AS (
+            INSERT INTO item (
+                item_id, chrt_id, track_number, price, rid, name, sale, size, total_price, nm_id, brand, status
+            )
+            SELECT item_id, chrt_id, track_number, price, rid, name, sale, size, total_price, nm_id, brand, status
+            FROM new_item
+        )
+        INSERT INTO order_item (order_uid, item_id)
+        SELECT order_uid, item_id FROM new_item
+    "#, client).await?;
+
+    client.execute(&statement, &[
+        &order_uids,
+        &column(&items, |item| &item.chrt_id),
+        &column(&items, |item| &item.track_number),
+        &column(&items, |item| &item.price),
+        &column(&items, |item| &item.rid),
+        &column(&items, |item| &item.name),
+        &column(&items, |item| &item.sale),
+        &column(&items, |item| &item.size),
+        &column(&items, |item| &item.total_price),
+        &column(&items, |item| &item.nm_id),
+        &column(&items, |item| &item.brand),
+        &column(&items, |item| &item.status),
+    ]).await?;
+
+    info!("Successfully added {} items", items.len());
+    Ok(())
+}
+
+// Начальные записи истории статусов товаров новых заказов
+fn initial_item_statuses<'a>(orders: &[&'a Order]) -> Result<Vec<(&'a String, StatusChange)>, Error> {
+    let mut history = Vec::new();
+    for order in orders {
+        history.extend(item_status_changes(&[], order)?.into_iter().map(|change| (&order.order_uid, change)));
+    }
+    Ok(history)
+}
+
+// Асинхронная функция для записи истории статусов товаров (UID заказа и изменение) одним запросом
+async fn insert_item_status_history(history: &[(&String, StatusChange)], client: &impl GenericClient, statements: &StatementCache) -> Result<(), Error> {
+    if history.is_empty() {
+        return Ok(());
+    }
+
+    let statement = statements.prepare(r#"
+        INSERT INTO item_status_history (order_uid, chrt_id, old_status, new_status)
+        SELECT * FROM unnest($1::text[], $2::bigint[], $3::int[], $4::int[])
+    "#, client).await?;
+    client.execute(&statement, &[
+        &history.iter().map(|(order_uid, _)| *order_uid).collect::<Vec<_>>(),
+        &history.iter().map(|(_, (chrt_id, _, _))| *chrt_id).collect::<Vec<_>>(),
+        &history.iter().map(|(_, (_, old_status, _))| *old_status).collect::<Vec<_>>(),
+        &history.iter().map(|(_, (_, _, new_status))| *new_status).collect::<Vec<_>>(),
+    ]).await?;
+    Ok(())
+}
+
+// Асинхронная функция для изменения заказа: информация о заказе, доставке и оплате обновляется,
+// набор товаров заменяется целиком, смена статусов товаров проверяется и записывается в историю.
+// Все изменения выполняются в одной транзакции
+pub async fn update_order<C: GenericClient>(order: &Order, client: &mut C, statements: &StatementCache) -> Result<(), Error> {
+    let _timer = metrics::db_timer("update_order");
+    info!("Updating order with ID: {:?}", order.order_uid); // Логируем изменение заказа
+
+    // Открываем транзакцию (при drop без commit она будет откачена)
+    let transaction = client.transaction().await?;
+    lock_order(&order.order_uid, &transaction, statements).await?;
+
+    // Получаем ссылки заказа на доставку и оплату
+    let (delivery_id, old_payment_transaction) = get_order_references(&order.order_uid, &transaction).await?;
+
+    // Смена статусов сохраненных товаров проверяется так же, как при переходе через transition_item_status
+    let current = get_order_item_statuses(&order.order_uid, &transaction).await?;
+    let history: Vec<_> = item_status_changes(&current, order)?
+        .into_iter()
+        .map(|change| (&order.order_uid, change))
+        .collect();
+
+    // Обновляем информацию о доставке (или создаем, если ссылка была потеряна)
+    let delivery_id = match delivery_id {
+        Some(delivery_id) => {
+            update_delivery(&order.delivery, delivery_id, &transaction).await?;
+            delivery_id
+        }
+        None => insert_delivery(&order.delivery, &transaction, statements).await?,
+    };
+
+    // Обновляем информацию об оплате. Идентификатор оплаты является первичным ключом,
+    // поэтому при его смене создается новая оплата, а старая удаляется после переключения ссылки
+    let payment_changed = old_payment_transaction.as_ref() != Some(&order.payment.transaction);
+    if payment_changed {
+        insert_payment(&order.payment, &transaction, statements).await?;
+    } else {
+        update_payment(&order.payment, &transaction).await?;
+    }
+
+    // Обновляем информацию о заказе
+    update_order_info(order, delivery_id, &transaction).await?;
+
+    if let Some(old_payment_transaction) = old_payment_transaction.filter(|_| payment_changed) {
+        transaction.execute("DELETE FROM payment WHERE transaction = $1", &[&old_payment_transaction]).await?;
+    }
+
+    // Заменяем набор товаров заказа и записываем изменения статусов в историю
+    delete_order_items(&order.order_uid, &transaction).await?;
+    insert_items(&[order], &transaction, statements).await?;
+    insert_item_status_history(&history, &transaction, statements).await?;
+
+    // Фиксируем транзакцию
+    transaction.commit().await?;
+
+    info!("Successfully updated order with ID: {:?}", order.order_uid); // Логируем успешное изменение заказа
+    Ok(())
+}
+
+// Асинхронная функция для удаления заказа вместе с доставкой, оплатой и товарами
+pub async fn delete_order<C: GenericClient>(order_uid: &String, client: &mut C, statements: &StatementCache) -> Result<(), Error> {
+    let _timer = metrics::db_timer("delete_order");
+    info!("Deleting order with ID: {:?}", order_uid); // Логируем удаление заказа
+
+    // Открываем транзакцию (при drop без commit она будет откачена)
+    let transaction = client.transaction().await?;
+    lock_order(order_uid, &transaction, statements).await?;
+
+    let (delivery_id, payment_transaction) = get_order_references(order_uid, &transaction).await?;
+
+    // Сначала удаляются товары и сам заказ (связи order_item удаляются каскадно), затем доставка и оплата
+    delete_order_items(order_uid, &transaction).await?;
+    transaction.execute("DELETE FROM order_info WHERE order_uid = $1", &[order_uid]).await?;
+    transaction.execute("DELETE FROM delivery WHERE delivery_id = $1", &[&delivery_id]).await?;
+    transaction.execute("DELETE FROM payment WHERE transaction = $1", &[&payment_transaction]).await?;
+
+    // Фиксируем транзакцию
+    transaction.commit().await?;
+
+    info!("Successfully deleted order with ID: {:?}", order_uid); // Логируем успешное удаление заказа
+    Ok(())
+}
+
+// Асинхронная функция для получения ссылок заказа на доставку и оплату (с блокировкой строки заказа)
+async fn get_order_references(order_uid: &String, client: &impl GenericClient) -> Result<(Option<i64>, Option<String>), Error> {
+    let row = client.query_opt(
+        "SELECT delivery_id, payment_transaction FROM order_info WHERE order_uid = $1 FOR UPDATE",
+        &[order_uid],
+    ).await?
+        .ok_or_else(|| Error::order_not_found(order_uid))?;
+    Ok((row.get(0), row.get(1)))
+}
+
+// Асинхронная функция для получения chrt_id и статусов товаров заказа
+async fn get_order_item_statuses(order_uid: &String, client: &impl GenericClient) -> Result<Vec<(i64, i32)>, Error> {
+    let rows = client.query(
+        "SELECT i.chrt_id, i.status FROM item i JOIN order_item oi ON i.item_id = oi.item_id WHERE oi.order_uid = $1",
+        &[order_uid],
+    ).await?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+// Асинхронная функция для удаления всех товаров заказа (связи order_item удаляются каскадно)
+async fn delete_order_items(order_uid: &String, client: &impl GenericClient) -> Result<(), Error> {
+    info!("Deleting items for order with ID: {:?}", order_uid);
+    client.execute(
+        "DELETE FROM item WHERE item_id IN (SELECT item_id FROM order_item WHERE order_uid = $1)",
+        &[order_uid],
+    ).await?;
+    Ok(())
+}
+
+// Асинхронная функция для изменения информации о доставке
+async fn update_delivery(delivery: &Delivery, delivery_id: i64, client: &impl GenericClient) -> Result<(), Error> {
+    info!("Updating delivery with ID: {:?}", delivery_id);
 
-    // SQL-запрос для вставки данных о товаре
     let query = r#"
-        INSERT INTO item (
-            chrt_id,
-            track_number,
-            price,
-            rid,
-            name,
-            sale,
-            size,
-            total_price,
-            nm_id,
-            brand,
-            status
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        UPDATE delivery SET
+            name = $2,
+            phone = $3,
+            zip = $4,
+            city = $5,
+            address = $6,
+            region = $7,
+            email = $8
+        WHERE delivery_id = $1
     "#;
-
-    // Выполняем запрос с передачей параметров
     client.execute(query, &[
-        &item.chrt_id,
-        &item.track_number,
-        &item.price,
-        &item.rid,
-        &item.name,
-        &item.sale,
-        &item.size,
-        &item.total_price,
-        &item.nm_id,
-        &item.brand,
-        &item.status,
-    ]).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+        &delivery_id,
+        &delivery.name,
+        &delivery.phone,
+        &delivery.zip,
+        &delivery.city,
+        &delivery.address,
+        &delivery.region,
+        &delivery.email,
+    ]).await?;
+    Ok(())
+}
+
+// Асинхронная функция для изменения информации о платеже
+async fn update_payment(payment: &Payment, client: &impl GenericClient) -> Result<(), Error> {
+    info!("Updating payment with ID: {:?}", payment.transaction);
 
-    // Логируем успешное добавление товара
-    info!("Successfully added item with ID: {:?}", item.chrt_id);
+    let query = r#"
+        UPDATE payment SET
+            request_id = $2,
+            currency = $3,
+            provider = $4,
+            amount = $5,
+            payment_dt = $6,
+            bank = $7,
+            delivery_cost = $8,
+            goods_total = $9,
+            custom_fee = $10
+        WHERE transaction = $1
+    "#;
+    client.execute(query, &[
+        &payment.transaction,
+        &payment.request_id,
+        &payment.currency,
+        &payment.provider,
+        &payment.amount,
+        &payment.payment_dt,
+        &payment.bank,
+        &payment.delivery_cost,
+        &payment.goods_total,
+        &payment.custom_fee,
+    ]).await?;
     Ok(())
 }
 
-// Асинхронная функция для вставки связи между заказом и товаром в базу данных
-async fn insert_order_item(order: &Order, item: &Item, client: &Client) -> Result<(), Box<dyn Error>> {
-    // Логируем информацию о добавляемом элементе заказа
-    info!("Adding order item with order ID: {:?}, item ID: {:?}", order.order_uid, item.chrt_id);
+// Асинхронная функция для изменения информации о заказе
+async fn update_order_info(order: &Order, delivery_id: i64, client: &impl GenericClient) -> Result<(), Error> {
+    info!("Updating order info with ID: {:?}", order.order_uid);
 
-    // SQL-запрос для вставки связи между заказом и товаром
     let query = r#"
-        INSERT INTO order_item (
-        order_uid,
-        item_chrt_id
-        ) VALUES ($1, $2)
+        UPDATE order_info SET
+            track_number = $2,
+            entry = $3,
+            delivery_id = $4,
+            payment_transaction = $5,
+            locale = $6,
+            internal_signature = $7,
+            customer_id = $8,
+            delivery_service = $9,
+            shardkey = $10,
+            sm_id = $11,
+            date_created = $12,
+            oof_shard = $13,
+            created_at = COALESCE(CAST($14::text AS timestamptz), '-infinity')
+        WHERE order_uid = $1
     "#;
+    client.execute(query, &[
+        &order.order_uid,
+        &order.track_number,
+        &order.entry,
+        &delivery_id,
+        &order.payment.transaction,
+        &order.locale,
+        &order.internal_signature,
+        &order.customer_id,
+        &order.delivery_service,
+        &order.shardkey,
+        &order.sm_id,
+        &order.date_created,
+        &order.oof_shard,
+        &created_at(order),
+    ]).await?;
+    Ok(())
+}
 
-    // Выполняем запрос с передачей параметров
-    client.execute(query, &[&order.order_uid, &item.chrt_id]).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+// Асинхронная функция для перехода товаров заказа с данным chrt_id в новый статус.
+// Переход проверяется по допустимым переходам ItemStatus и записывается в историю
+pub async fn transition_item_status<C: GenericClient>(
+    order_uid: &String,
+    chrt_id: i64,
+    status: ItemStatus,
+    client: &mut C,
+    statements: &StatementCache,
+) -> Result<(), Error> {
+    let _timer = metrics::db_timer("transition_item_status");
+    info!("Changing status of item {:?} in order {:?} to {:?}", chrt_id, order_uid, status);
 
-    // Логируем успешное добавление элемента заказа
-    info!("Successfully added order item with order ID: {:?}, item ID: {:?}", order.order_uid, item.chrt_id);
+    // Открываем транзакцию (при drop без commit она будет откачена)
+    let transaction = client.transaction().await?;
+    lock_order(order_uid, &transaction, statements).await?;
+
+    let items = get_item_statuses(order_uid, chrt_id, &transaction).await?;
+
+    for (item_id, current) in items {
+        check_item_transition(chrt_id, current, status)?;
+
+        transaction.execute("UPDATE item SET status = $2 WHERE item_id = $1", &[&item_id, &status.code()]).await?;
+        transaction.execute(
+            "INSERT INTO item_status_history (order_uid, chrt_id, old_status, new_status) VALUES ($1, $2, $3, $4)",
+            &[order_uid, &chrt_id, &current, &status.code()],
+        ).await?;
+    }
+
+    // Фиксируем транзакцию
+    transaction.commit().await?;
+
+    info!("Successfully changed status of item {:?} in order {:?}", chrt_id, order_uid);
     Ok(())
 }
 
-// Асинхронная функция для получения заказа по уникальному идентификатору (UID)
-pub async fn get_order_by_uid(order_uid: &String, client: &Client) -> Result<Order, Box<dyn Error>> {
-    // Логируем информацию о запрашиваемом заказе
-    info!("Getting order with ID: {:?}", order_uid);
-    
-    // SQL-запрос для получения информации о заказе и связанных данных
+// Проверка перехода товара из статуса с кодом current в status.
+// Переход разрешен только из известного статуса в один из следующих за ним
+pub fn check_item_transition(chrt_id: i64, current: i32, status: ItemStatus) -> Result<(), Error> {
+    let allowed = ItemStatus::from_code(current).map(ItemStatus::next).unwrap_or_default();
+    if allowed.contains(&status) {
+        return Ok(());
+    }
+
+    Err(Error::Conflict {
+        message: format!("Item {} cannot change status from {} to {}", chrt_id, current, status.code()),
+        details: json!({
+            "from": ItemStatus::from_code(current),
+            "from_code": current,
+            "to": status,
+            "allowed": allowed,
+        }),
+    })
+}
+
+// Запись истории статуса товара: chrt_id, предыдущий статус (None для нового товара) и новый статус
+pub type StatusChange = (i64, Option<i32>, i32);
+
+// Изменения статусов товаров при записи заказа целиком (добавление, PUT, PATCH). current - chrt_id и статусы
+// сохраненных товаров заказа (пусто для нового заказа). Товар, которого не было в заказе, получает начальную запись
+// истории без предыдущего статуса, смена статуса сохраненного товара проверяется по допустимым переходам ItemStatus.
+// Возвращает записи истории без повторов
+pub fn item_status_changes(current: &[(i64, i32)], order: &Order) -> Result<Vec<StatusChange>, Error> {
+    let mut changes: Vec<StatusChange> = Vec::new();
+
+    for item in &order.items {
+        let old_status = current.iter()
+            .find(|(chrt_id, _)| *chrt_id == item.chrt_id)
+            .map(|&(_, status)| status);
+        if old_status == Some(item.status) {
+            continue;
+        }
+
+        if let Some(old_status) = old_status {
+            let status = ItemStatus::from_code(item.status).ok_or_else(|| Error::Validation {
+                message: format!("Item {} cannot change status to unknown status {}", item.chrt_id, item.status),
+                details: json!({ "chrt_id": item.chrt_id, "status": item.status }),
+            })?;
+            check_item_transition(item.chrt_id, old_status, status)?;
+        }
+
+        let change = (item.chrt_id, old_status, item.status);
+        if !changes.contains(&change) {
+            changes.push(change);
+        }
+    }
+
+    Ok(changes)
+}
+
+// Асинхронная функция для получения текущего статуса товара и истории его изменений (от старых к новым)
+pub async fn get_item_status_history(
+    order_uid: &String,
+    chrt_id: i64,
+    client: &impl GenericClient,
+) -> Result<(i32, Vec<ItemStatusChange>), Error> {
+    let _timer = metrics::db_timer("get_item_status_history");
+    info!("Getting status history of item {:?} in order {:?}", chrt_id, order_uid);
+
+    let (_, status) = get_item_statuses(order_uid, chrt_id, client).await?[0];
+
+    let query = r#"
+        SELECT
+            old_status, new_status,
+            to_char(changed_at, 'YYYY-MM-DD"T"HH24:MI:SS.USOF') AS changed_at
+        FROM
+            item_status_history
+        WHERE
+            order_uid = $1 AND chrt_id = $2
+        ORDER BY
+            id
+    "#;
+    let rows = client.query(query, &[order_uid, &chrt_id]).await?;
+    let history = rows.iter()
+        .map(|row| ItemStatusChange {
+            old_status: row.get("old_status"),
+            new_status: row.get("new_status"),
+            changed_at: row.get("changed_at"),
+        })
+        .collect();
+
+    Ok((status, history))
+}
+
+// Асинхронная функция для получения ID и статусов товаров заказа с данным chrt_id (с блокировкой строк)
+async fn get_item_statuses(order_uid: &String, chrt_id: i64, client: &impl GenericClient) -> Result<Vec<(i64, i32)>, Error> {
     let query = r#"
-            SELECT 
-                oi.order_uid, oi.track_number, oi.entry, oi.locale, oi.internal_signature, 
-                oi.customer_id, oi.delivery_service, oi.shardkey, oi.sm_id, oi.date_created, 
-                oi.oof_shard, d.delivery_id, d.name, d.phone, d.zip, d.city, d.address, 
-                d.region, d.email, p.transaction, p.request_id, p.currency, p.provider, 
-                p.amount, p.payment_dt, p.bank, p.delivery_cost, p.goods_total, p.custom_fee
-            FROM 
+        SELECT i.item_id, i.status
+        FROM item i
+        JOIN order_item oi ON i.item_id = oi.item_id
+        WHERE oi.order_uid = $1 AND i.chrt_id = $2
+        ORDER BY i.item_id
+        FOR UPDATE OF i
+    "#;
+    let rows = client.query(query, &[order_uid, &chrt_id]).await?;
+    if rows.is_empty() {
+        return Err(Error::item_not_found(order_uid, chrt_id));
+    }
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+// SQL-запрос для получения информации о заказах и связанных данных (без товаров)
+const SELECT_ORDER: &str = r#"
+            SELECT
+                json_build_object(
+                    'order_uid', oi.order_uid,
+                    'track_number', oi.track_number,
+                    'entry', oi.entry,
+                    'delivery', json_build_object(
+                        'name', d.name,
+                        'phone', d.phone,
+                        'zip', d.zip,
+                        'city', d.city,
+                        'address', d.address,
+                        'region', d.region,
+                        'email', d.email
+                    ),
+                    'payment', json_build_object(
+                        'transaction', p.transaction,
+                        'request_id', p.request_id,
+                        'currency', p.currency,
+                        'provider', p.provider,
+                        'amount', p.amount,
+                        'payment_dt', p.payment_dt,
+                        'bank', p.bank,
+                        'delivery_cost', p.delivery_cost,
+                        'goods_total', p.goods_total,
+                        'custom_fee', p.custom_fee
+                    ),
+                    'items', COALESCE((
+                        SELECT
+                            json_agg(json_build_object(
+                                'chrt_id', i.chrt_id,
+                                'track_number', i.track_number,
+                                'price', i.price,
+                                'rid', i.rid,
+                                'name', i.name,
+                                'sale', i.sale,
+                                'size', i.size,
+                                'total_price', i.total_price,
+                                'nm_id', i.nm_id,
+                                'brand', i.brand,
+                                'status', i.status
+                            ) ORDER BY i.item_id)
+                        FROM
+                            order_item x
+                        JOIN
+                            item i ON i.item_id = x.item_id
+                        WHERE
+                            x.order_uid = oi.order_uid
+                    ), '[]'::json),
+                    'locale', oi.locale,
+                    'internal_signature', oi.internal_signature,
+                    'customer_id', oi.customer_id,
+                    'delivery_service', oi.delivery_service,
+                    'shardkey', oi.shardkey,
+                    'sm_id', oi.sm_id,
+                    'date_created', oi.date_created,
+                    'oof_shard', oi.oof_shard
+                ) AS order_json
+            FROM
                 order_info oi
-            JOIN 
+            JOIN
                 delivery d ON oi.delivery_id = d.delivery_id
-            JOIN 
+            JOIN
                 payment p ON oi.payment_transaction = p.transaction
-            WHERE 
-                oi.order_uid = $1
             "#;
 
-    // Выполняем запрос и получаем одну строку результата
-    let row = client.query_one(query, &[&order_uid]).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+// Асинхронная функция для получения заказа по уникальному идентификатору (UID).
+// Заказ вместе с доставкой, оплатой и товарами собирается в JSON одним запросом
+pub async fn get_order_by_uid(order_uid: &String, client: &impl GenericClient) -> Result<Order, Error> {
+    let _timer = metrics::db_timer("get_order_by_uid");
+    // Логируем информацию о запрашиваемом заказе
+    info!("Getting order with ID: {:?}", order_uid);
+
+    // SQL-запрос для получения заказа со всеми связанными данными
+    let query = format!("{} WHERE oi.order_uid = $1", SELECT_ORDER);
+
+    // Выполняем запрос и получаем одну строку результата, отсутствие строки означает, что заказа нет
+    let row = client.query_opt(&query, &[&order_uid]).await?  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+        .ok_or_else(|| Error::order_not_found(order_uid))?;
 
     // Преобразуем строку результата в структуру Order
-    let mut order = map_order_from_row(&row);
-    
-    // Получаем товары, связанные с заказом
-    order.items = get_items_for_order(&order.order_uid, client).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+    let order = map_order_from_row(&row)?;
 
     // Логируем успешное получение заказа
     info!("Successfully got order with ID: {:?}", order_uid);
     Ok(order)
 }
 
-// Асинхронная функция для получения товаров, связанных с заказом
-async fn get_items_for_order(order_uid: &String, client: &Client) -> Result<Vec<Item>, Box<dyn Error>> {
-    // Логируем информацию о запрашиваемых товарах для заказа
-    info!("Getting items for order with ID: {:?}", order_uid);
+// Асинхронная функция для получения нескольких заказов по списку UID одним запросом.
+// Заказы возвращаются в порядке списка, отсутствующие заказы пропускаются
+pub async fn get_orders_by_uids(order_uids: &[String], client: &impl GenericClient) -> Result<Vec<Order>, Error> {
+    let _timer = metrics::db_timer("get_orders_by_uids");
+    info!("Getting {} orders by ID", order_uids.len());
 
-    // SQL-запрос для получения информации о товарах, связанных с заказом
-    let query = r#"
-            SELECT 
-                i.chrt_id, i.track_number, i.price, i.rid, i.name, i.sale, 
-                i.size, i.total_price, i.nm_id, i.brand, i.status 
-            FROM 
-                item i 
-            JOIN 
-                order_item oi ON i.chrt_id = oi.item_chrt_id 
-            WHERE 
-                oi.order_uid = $1
-            "#;
-    
-    // Выполняем запрос и получаем все строки результата
-    let rows = client.query(query, &[&order_uid]).await?;  // '?' указывает на то, что при возврате ошибки, она прокинется наверх к вызывающей стороне
+    let query = format!("{} WHERE oi.order_uid = ANY($1)", SELECT_ORDER);
+    let rows = client.query(&query, &[&order_uids]).await?;
+    let orders = rows.iter().map(map_order_from_row).collect::<Result<Vec<_>, _>>()?;
 
-    // Создаем вектор для хранения товаров
-    let mut items = Vec::new();
+    info!("Successfully got {} orders by ID", orders.len());
+    Ok(sort_by_uids(orders, order_uids))
+}
 
-    // Проходим по всем строкам результата и преобразуем их в структуры Item
-    for row in rows {
-        let item = map_item_from_row(&row);
-        items.push(item);
-    }
+// Упорядочивание заказов по списку UID, заказы, которых нет в списке, отбрасываются
+pub(crate) fn sort_by_uids(orders: Vec<Order>, order_uids: &[String]) -> Vec<Order> {
+    let mut orders: HashMap<String, Order> = orders.into_iter().map(|order| (order.order_uid.clone(), order)).collect();
+    order_uids.iter().filter_map(|order_uid| orders.remove(order_uid)).collect()
+}
+
+// Асинхронная функция для получения UID самых новых заказов (по моменту создания)
+pub async fn get_recent_order_uids(limit: i64, client: &impl GenericClient) -> Result<Vec<String>, Error> {
+    let _timer = metrics::db_timer("get_recent_order_uids");
+    info!("Getting {:?} recent order IDs", limit);
 
-    // Логируем успешное получение товаров для заказа
-    info!("Successfully got items for order with ID: {:?}", order_uid);
-
-    Ok(items)
-}
-
-fn map_item_from_row(row: &tokio_postgres::Row) -> Item {
-    Item {
-        chrt_id: row.get("chrt_id"),
-        track_number: row.get("track_number"),
-        price: row.get("price"),
-        rid: row.get("rid"),
-        name: row.get("name"),
-        sale: row.get("sale"),
-        size: row.get("size"),
-        total_price: row.get("total_price"),
-        nm_id: row.get("nm_id"),
-        brand: row.get("brand"),
-        status: row.get("status"),
+    let rows = client.query(
+        "SELECT order_uid FROM order_info ORDER BY created_at DESC, order_uid LIMIT $1",
+        &[&limit],
+    ).await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+// Фильтры и параметры постраничного вывода списка заказов
+#[derive(Debug, Default, Clone)]
+pub struct OrderFilter {
+    pub customer_id: Option<String>,
+    pub track_number: Option<String>,
+    pub delivery_service: Option<String>,
+    pub payment_provider: Option<String>,
+    pub created_from: Option<String>, // Начало диапазона date_created (RFC 3339, включительно)
+    pub created_to: Option<String>, // Конец диапазона date_created (RFC 3339, не включительно)
+    pub brand: Option<String>, // Бренд хотя бы одного товара заказа
+    pub nm_id: Option<i64>, // nm_id хотя бы одного товара заказа
+    pub after: Option<(Option<DateTime<Utc>>, String)>, // Курсор: (Order::created_at, order_uid) последнего заказа предыдущей страницы
+    pub descending: bool, // Сортировка по моменту создания от новых к старым
+    pub limit: i64, // Количество заказов на странице
+}
+
+// Асинхронная функция для получения списка заказов по фильтрам с курсорной пагинацией.
+// Заказы сортируются по (created_at, order_uid), страница вместе с товарами загружается одним запросом
+pub async fn list_orders(filter: &OrderFilter, client: &impl GenericClient) -> Result<Vec<Order>, Error> {
+    let _timer = metrics::db_timer("list_orders");
+    info!("Listing orders with filter: {:?}", filter);
+
+    let after = filter.after.as_ref().map(|(created_at, order_uid)| (created_at.map(timestamp), order_uid));
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+
+    if let Some(customer_id) = &filter.customer_id {
+        conditions.push(format!("oi.customer_id = {}", bind(&mut params, customer_id)));
+    }
+    if let Some(track_number) = &filter.track_number {
+        conditions.push(format!("oi.track_number = {}", bind(&mut params, track_number)));
+    }
+    if let Some(delivery_service) = &filter.delivery_service {
+        conditions.push(format!("oi.delivery_service = {}", bind(&mut params, delivery_service)));
     }
+    if let Some(payment_provider) = &filter.payment_provider {
+        conditions.push(format!("p.provider = {}", bind(&mut params, payment_provider)));
+    }
+    if let Some(created_from) = &filter.created_from {
+        conditions.push(format!("oi.created_at >= CAST({}::text AS timestamptz)", bind(&mut params, created_from)));
+    }
+    if let Some(created_to) = &filter.created_to {
+        conditions.push(format!("oi.created_at < CAST({}::text AS timestamptz)", bind(&mut params, created_to)));
+    }
+    if let Some(brand) = &filter.brand {
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM order_item f JOIN item i ON i.item_id = f.item_id WHERE f.order_uid = oi.order_uid AND i.brand = {})",
+            bind(&mut params, brand),
+        ));
+    }
+    if let Some(nm_id) = &filter.nm_id {
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM order_item f JOIN item i ON i.item_id = f.item_id WHERE f.order_uid = oi.order_uid AND i.nm_id = {})",
+            bind(&mut params, nm_id),
+        ));
+    }
+    // Курсор: заказы строго после последнего заказа предыдущей страницы в порядке сортировки
+    if let Some((created_at, order_uid)) = &after {
+        conditions.push(format!(
+            "(oi.created_at, oi.order_uid) {} (COALESCE(CAST({}::text AS timestamptz), '-infinity'), {})",
+            if filter.descending { "<" } else { ">" },
+            bind(&mut params, created_at),
+            bind(&mut params, order_uid),
+        ));
+    }
+    let limit = bind(&mut params, &filter.limit);
+
+    let direction = if filter.descending { "DESC" } else { "ASC" };
+    let query = format!(
+        "{} {} ORDER BY oi.created_at {}, oi.order_uid {} LIMIT {}",
+        SELECT_ORDER,
+        if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) },
+        direction,
+        direction,
+        limit,
+    );
+
+    let rows = client.query(&query, &params).await?;
+    let orders = rows.iter().map(map_order_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    info!("Successfully listed {} orders", orders.len());
+    Ok(orders)
 }
 
-// Маппинг заказа из строки, полученного из таблицы
-fn map_order_from_row(row: &tokio_postgres::Row) -> Order {
-    let delivery =     Delivery {
-        name: row.get("name"),
-        phone: row.get("phone"),
-        zip: row.get("zip"),
-        city: row.get("city"),
-        address: row.get("address"),
-        region: row.get("region"),
-        email: row.get("email"),
-    };
+// Добавление параметра запроса, возвращает его плейсхолдер ($1, $2, ...)
+fn bind<'a>(params: &mut Vec<&'a (dyn ToSql + Sync)>, value: &'a (dyn ToSql + Sync)) -> String {
+    params.push(value);
+    format!("${}", params.len())
+}
 
-    let payment =      Payment {
-        transaction: row.get("transaction"),
-        request_id: row.get("request_id"),
-        currency: row.get("currency"),
-        provider: row.get("provider"),
-        amount: row.get("amount"),
-        payment_dt: row.get("payment_dt"),
-        bank: row.get("bank"),
-        delivery_cost: row.get("delivery_cost"),
-        goods_total: row.get("goods_total"),
-        custom_fee: row.get("custom_fee"),
-    };
+// Заказ из JSON, собранного запросом SELECT_ORDER
+fn map_order_from_row(row: &tokio_postgres::Row) -> Result<Order, Error> {
+    let Json(order) = row.try_get("order_json")?;
+    Ok(order)
+}
+
+// Асинхронная функция для сохранения отклоненного сообщения с заказом
+pub async fn insert_dead_letter(payload: &str, reason: &str, source: &str, client: &impl GenericClient) -> Result<i64, Error> {
+    let _timer = metrics::db_timer("insert_dead_letter");
+    info!("Adding dead letter from {:?}", source);
+
+    let query = r#"
+        INSERT INTO dead_letter (payload, reason, source)
+        VALUES ($1, $2, $3)
+        RETURNING id
+    "#;
+    let row = client.query_one(query, &[&payload, &reason, &source]).await?;
+    let id: i64 = row.get(0);
+
+    info!("Successfully added dead letter with ID: {:?}", id);
+    Ok(id)
+}
+
+// SQL-запрос для выборки отклоненных сообщений
+const SELECT_DEAD_LETTER: &str = r#"
+    SELECT
+        id, payload, reason, source,
+        to_char(created_at, 'YYYY-MM-DD"T"HH24:MI:SS.USOF') AS created_at
+    FROM
+        dead_letter
+"#;
+
+// Асинхронная функция для получения списка отклоненных сообщений (от новых к старым)
+pub async fn list_dead_letters(limit: i64, offset: i64, client: &impl GenericClient) -> Result<Vec<DeadLetter>, Error> {
+    let _timer = metrics::db_timer("list_dead_letters");
+    info!("Getting dead letters, limit: {:?}, offset: {:?}", limit, offset);
+
+    let query = format!("{} ORDER BY id DESC LIMIT $1 OFFSET $2", SELECT_DEAD_LETTER);
+    let rows = client.query(&query, &[&limit, &offset]).await?;
+
+    Ok(rows.iter().map(map_dead_letter_from_row).collect())
+}
+
+// Асинхронная функция для получения отклоненного сообщения по ID
+pub async fn get_dead_letter(id: i64, client: &impl GenericClient) -> Result<DeadLetter, Error> {
+    let _timer = metrics::db_timer("get_dead_letter");
+    info!("Getting dead letter with ID: {:?}", id);
+
+    let query = format!("{} WHERE id = $1", SELECT_DEAD_LETTER);
+    let row = client.query_opt(&query, &[&id]).await?
+        .ok_or_else(|| Error::dead_letter_not_found(id))?;
+
+    Ok(map_dead_letter_from_row(&row))
+}
+
+// Асинхронная функция для обновления причины отклонения (после неудачной повторной обработки)
+pub async fn update_dead_letter_reason(id: i64, reason: &str, client: &impl GenericClient) -> Result<(), Error> {
+    let _timer = metrics::db_timer("update_dead_letter_reason");
+    info!("Updating dead letter with ID: {:?}", id);
+    client.execute("UPDATE dead_letter SET reason = $2 WHERE id = $1", &[&id, &reason]).await?;
+    Ok(())
+}
+
+// Асинхронная функция для удаления отклоненного сообщения (после успешной повторной обработки)
+pub async fn delete_dead_letter(id: i64, client: &impl GenericClient) -> Result<(), Error> {
+    let _timer = metrics::db_timer("delete_dead_letter");
+    info!("Deleting dead letter with ID: {:?}", id);
+    client.execute("DELETE FROM dead_letter WHERE id = $1", &[&id]).await?;
+    Ok(())
+}
 
-    Order {
-        order_uid: row.get("order_uid"),
-        track_number: row.get("track_number"),
-        entry: row.get("entry"),
-        locale: row.get("locale"),
-        internal_signature: row.get("internal_signature"),
-        customer_id: row.get("customer_id"),
-        delivery_service: row.get("delivery_service"),
-        shardkey: row.get("shardkey"),
-        sm_id: row.get("sm_id"),
-        date_created: row.get("date_created"),
-        oof_shard: row.get("oof_shard"),
-        delivery,
-        payment,
-        items: Vec::new(),
+// Маппинг отклоненного сообщения из строки, полученной из таблицы
+fn map_dead_letter_from_row(row: &tokio_postgres::Row) -> DeadLetter {
+    DeadLetter {
+        id: row.get("id"),
+        payload: row.get("payload"),
+        reason: row.get("reason"),
+        source: row.get("source"),
+        created_at: row.get("created_at"),
     }
 }
@@ -0,0 +1,109 @@
+use axum::{
+    extract::{rejection::{PathRejection, QueryRejection}, Json, Path, Query, State},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use log::{info, error}; // Макросы для логирования
+use serde::Deserialize;
+
+use crate::error::Error; // Тип ошибки приложения
+use crate::model::Order; // Модель заказа
+use crate::orders::invalid_param; // Ошибка некорректного параметра запроса
+use crate::AppState; // Состояние приложения
+
+// Количество отклоненных сообщений в списке по умолчанию и максимальное
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+// Сохранение отклоненного сообщения с заказом.
+// Не сохраняется только повтор заказа с уже сохраненным UID: заказ уже есть в хранилище.
+// Остальные конфликты (например, занятый идентификатор оплаты другим заказом) и ошибки хранилища
+// сохраняются, чтобы заказ можно было обработать повторно после устранения причины
+pub async fn store(state: &AppState, payload: &[u8], reason: &Error, source: &str) {
+    if reason.is_order_conflict() {
+        return;
+    }
+
+    let payload = String::from_utf8_lossy(payload);
+    match state.repository.insert_dead_letter(&payload, &describe(reason), source).await {
+        Ok(id) => info!("Rejected payload from {} stored as dead letter {}", source, id),
+        // Сообщение теряется только если недоступно само хранилище
+        Err(e) => error!("Failed to store dead letter from {}: {:?}, payload: {}", source, e, payload),
+    }
+}
+
+// Причина отклонения: сообщение об ошибке и дополнительные сведения, если они есть
+fn describe(reason: &Error) -> String {
+    match reason.details() {
+        serde_json::Value::Null => reason.to_string(),
+        details => format!("{}: {}", reason, details),
+    }
+}
+
+// Параметры постраничного вывода списка
+#[derive(Deserialize)]
+pub struct ListParams {
+    limit: Option<i64>, // Количество записей
+    offset: Option<i64>, // Смещение от начала списка
+}
+
+// Асинхронная функция для получения списка отклоненных сообщений
+pub async fn list(
+    params: Result<Query<ListParams>, QueryRejection>, // Извлекаем параметры постраничного вывода
+    State(state): State<AppState>, // Извлекаем состояние приложения
+) -> Result<impl IntoResponse, Error> {
+    let Query(params) = params?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(invalid_param("limit", format!("must be between 1 and {}", MAX_LIMIT)));
+    }
+    let offset = params.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(invalid_param("offset", "must not be negative"));
+    }
+
+    let dead_letters = state.repository.list_dead_letters(limit, offset).await?;
+    Ok(Json(dead_letters))
+}
+
+// Асинхронная функция для получения отклоненного сообщения по ID
+pub async fn get(
+    path: Result<Path<i64>, PathRejection>, // Извлекаем ID из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+) -> Result<impl IntoResponse, Error> {
+    let Path(id) = path?;
+    let dead_letter = state.repository.get_dead_letter(id).await?;
+    Ok(Json(dead_letter))
+}
+
+// Асинхронная функция для повторной обработки отклоненного сообщения.
+// При успехе сообщение удаляется и возвращается добавленный заказ,
+// при ошибке у сообщения обновляется причина отклонения
+pub async fn replay(
+    path: Result<Path<i64>, PathRejection>, // Извлекаем ID из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+) -> Result<impl IntoResponse, Error> {
+    let Path(id) = path?;
+    info!("Replaying dead letter {}", id);
+
+    let dead_letter = state.repository.get_dead_letter(id).await?;
+
+    let result = match serde_json::from_str::<Order>(&dead_letter.payload) {
+        Ok(order) => crate::ingest_order(&state, order, state.ingest_mode).await,
+        Err(e) => Err(e.into()),
+    };
+
+    match result {
+        Ok(order) => {
+            state.repository.delete_dead_letter(id).await?;
+            info!("Dead letter {} replayed as order {:?}", id, order.order_uid);
+            Ok((StatusCode::OK, serde_json::to_string_pretty(&order).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to replay dead letter {}: {:?}", id, e);
+            state.repository.update_dead_letter_reason(id, &describe(&e)).await?;
+            Err(e)
+        }
+    }
+}
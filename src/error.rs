@@ -0,0 +1,248 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use log::error; // Макрос для логирования ошибок
+use rusqlite::ffi;
+use tokio_postgres::error::SqlState;
+
+// Ошибки приложения. Каждый вариант соответствует своему HTTP-статусу,
+// ответ с ошибкой всегда имеет вид {"code": ..., "message": ..., "details": ...}
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    // Запрошенная сущность не найдена (404)
+    #[error("{message}")]
+    NotFound { message: String },
+
+    // Сущность с таким ключом уже существует (409)
+    #[error("{message}")]
+    Conflict { message: String, details: Value },
+
+    // Некорректные входные данные (422)
+    #[error("{message}")]
+    Validation { message: String, details: Value },
+
+    // Ошибка базы данных или инфраструктуры (500)
+    #[error("{0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    // Ошибка отсутствия заказа с данным UID
+    pub fn order_not_found(order_uid: &str) -> Self {
+        Error::NotFound {
+            message: format!("Order {} not found", order_uid),
+        }
+    }
+
+    // Ошибка отсутствия товара с данным chrt_id в заказе
+    pub fn item_not_found(order_uid: &str, chrt_id: i64) -> Self {
+        Error::NotFound {
+            message: format!("Item {} not found in order {}", chrt_id, order_uid),
+        }
+    }
+
+    // Ошибка занятого идентификатора оплаты (повторяет ошибку первичного ключа таблицы payment)
+    pub fn payment_conflict(transaction: &str) -> Self {
+        Error::Conflict {
+            message: "duplicate key value violates unique constraint \"payment_pkey\"".to_string(),
+            details: json!({
+                "constraint": "payment_pkey",
+                "table": "payment",
+                "detail": format!("Key (transaction)=({}) already exists.", transaction),
+            }),
+        }
+    }
+
+    // Ошибка отсутствия отклоненного сообщения с данным ID
+    pub fn dead_letter_not_found(id: i64) -> Self {
+        Error::NotFound {
+            message: format!("Dead letter {} not found", id),
+        }
+    }
+
+    // Ошибка повторного добавления заказа с данным UID
+    pub fn order_conflict(order_uid: &str) -> Self {
+        Error::Conflict {
+            message: format!("Order {} already exists", order_uid),
+            details: json!({ "order_uid": order_uid }),
+        }
+    }
+
+    // Является ли ошибка повтором заказа с уже сохраненным UID (см. order_conflict)
+    pub fn is_order_conflict(&self) -> bool {
+        matches!(self, Error::Conflict { details, .. } if details.get("order_uid").is_some())
+    }
+
+    // HTTP-статус, соответствующий ошибке
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::NotFound { .. } => StatusCode::NOT_FOUND,
+            Error::Conflict { .. } => StatusCode::CONFLICT,
+            Error::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Стабильный машиночитаемый код ошибки
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound { .. } => "not_found",
+            Error::Conflict { .. } => "conflict",
+            Error::Validation { .. } => "validation_error",
+            Error::Backend(_) => "internal_error",
+        }
+    }
+
+    // Дополнительные сведения об ошибке
+    pub fn details(&self) -> Value {
+        match self {
+            Error::Conflict { details, .. } | Error::Validation { details, .. } => details.clone(),
+            Error::NotFound { .. } | Error::Backend(_) => Value::Null,
+        }
+    }
+
+    // Сообщение для клиента: подробности ошибок базы данных и инфраструктуры не раскрываются
+    pub fn message(&self) -> String {
+        match self {
+            Error::Backend(_) => "Internal server error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    // Тело ответа с ошибкой
+    pub fn body(&self) -> Value {
+        json!({
+            "code": self.code(),
+            "message": self.message(),
+            "details": self.details(),
+        })
+    }
+}
+
+// Классификация ошибок PostgreSQL по коду SQLSTATE
+impl From<tokio_postgres::Error> for Error {
+    fn from(e: tokio_postgres::Error) -> Self {
+        let Some(db_error) = e.as_db_error() else {
+            return Error::Backend(e.into());
+        };
+
+        let details = json!({
+            "constraint": db_error.constraint(),
+            "table": db_error.table(),
+            "detail": db_error.detail(),
+        });
+
+        match *db_error.code() {
+            SqlState::UNIQUE_VIOLATION => Error::Conflict {
+                message: db_error.message().to_string(),
+                details,
+            },
+            SqlState::FOREIGN_KEY_VIOLATION
+            | SqlState::NOT_NULL_VIOLATION
+            | SqlState::CHECK_VIOLATION
+            | SqlState::STRING_DATA_RIGHT_TRUNCATION => Error::Validation {
+                message: db_error.message().to_string(),
+                details,
+            },
+            _ => Error::Backend(e.into()),
+        }
+    }
+}
+
+// Классификация ошибок SQLite по расширенному коду ошибки ограничения
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        let rusqlite::Error::SqliteFailure(failure, Some(message)) = &e else {
+            return Error::Backend(e.into());
+        };
+
+        // Сообщение имеет вид "UNIQUE constraint failed: payment.transaction"
+        let table = message.split_once(": ")
+            .and_then(|(_, columns)| columns.split_once('.'))
+            .map(|(table, _)| table);
+        let details = json!({
+            "constraint": None::<&str>,
+            "table": table,
+            "detail": message,
+        });
+
+        match failure.extended_code {
+            ffi::SQLITE_CONSTRAINT_PRIMARYKEY | ffi::SQLITE_CONSTRAINT_UNIQUE => Error::Conflict {
+                message: message.clone(),
+                details,
+            },
+            ffi::SQLITE_CONSTRAINT_FOREIGNKEY
+            | ffi::SQLITE_CONSTRAINT_NOTNULL
+            | ffi::SQLITE_CONSTRAINT_CHECK => Error::Validation {
+                message: message.clone(),
+                details,
+            },
+            _ => Error::Backend(e.into()),
+        }
+    }
+}
+
+// Ошибка получения соединения из пула
+impl From<bb8::RunError<tokio_postgres::Error>> for Error {
+    fn from(e: bb8::RunError<tokio_postgres::Error>) -> Self {
+        match e {
+            bb8::RunError::User(e) => e.into(),
+            bb8::RunError::TimedOut => Error::Backend("Timed out waiting for a database connection".into()),
+        }
+    }
+}
+
+// Тело запроса не удалось разобрать как JSON нужной структуры
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        Error::Validation {
+            message: "Invalid request body".to_string(),
+            details: json!({ "reason": rejection.body_text() }),
+        }
+    }
+}
+
+// Параметры запроса не удалось разобрать
+impl From<QueryRejection> for Error {
+    fn from(rejection: QueryRejection) -> Self {
+        Error::Validation {
+            message: "Invalid query parameters".to_string(),
+            details: json!({ "reason": rejection.body_text() }),
+        }
+    }
+}
+
+// Параметры пути не удалось разобрать (например, нечисловой идентификатор)
+impl From<PathRejection> for Error {
+    fn from(rejection: PathRejection) -> Self {
+        Error::Validation {
+            message: "Invalid path parameters".to_string(),
+            details: json!({ "reason": rejection.body_text() }),
+        }
+    }
+}
+
+// Данные не удалось разобрать как JSON заказа (например, сообщение из очереди)
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Validation {
+            message: "Invalid order JSON".to_string(),
+            details: json!({ "reason": e.to_string() }),
+        }
+    }
+}
+
+// Преобразование ошибки в HTTP-ответ
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        // Подробности внутренней ошибки остаются только в журнале сервера
+        if let Error::Backend(e) = &self {
+            error!("Internal error: {:?}", e);
+        }
+        (self.status(), Json(self.body())).into_response()
+    }
+}
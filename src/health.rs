@@ -0,0 +1,76 @@
+use std::time::Instant;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::{json, Value};
+
+use crate::AppState; // Состояние приложения
+
+// Асинхронная функция проверки работоспособности: процесс запущен и обрабатывает запросы
+pub async fn healthz() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+// Асинхронная функция проверки готовности: база данных доступна, миграции применены, кэш инициализирован.
+// Возвращает 200, если все проверки пройдены, иначе 503; результат каждой проверки указывается отдельно
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let database = check_database(&state).await;
+    let migrations = check_migrations(&state).await;
+    let cache = check_cache(&state);
+
+    let ready = [&database, &migrations, &cache].iter().all(|check| check["status"] == "up");
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (status, Json(json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": {
+            "database": database,
+            "migrations": migrations,
+            "cache": cache,
+        },
+    })))
+}
+
+// Проверка доступности базы данных легковесным запросом.
+// Пока соединение не восстановлено после потери, проверка не проходит
+async fn check_database(state: &AppState) -> Value {
+    if !state.db_health.is_connected() {
+        return json!({
+            "status": "down",
+            "reconnecting": true,
+            "error": state.db_health.last_error(),
+        });
+    }
+
+    let started = Instant::now();
+    match state.repository.ping().await {
+        Ok(()) => json!({ "status": "up", "latency_ms": started.elapsed().as_millis() as u64 }),
+        Err(e) => json!({ "status": "down", "error": e.to_string() }),
+    }
+}
+
+// Проверка того, что все встроенные миграции применены
+async fn check_migrations(state: &AppState) -> Value {
+    match state.repository.migration_status().await {
+        Ok(statuses) => {
+            let pending: Vec<i64> = statuses.iter()
+                .filter(|status| status.applied_on.is_none())
+                .map(|status| status.migration.version)
+                .collect();
+            json!({
+                "status": if pending.is_empty() { "up" } else { "down" },
+                "applied": statuses.len() - pending.len(),
+                "pending": pending,
+            })
+        }
+        Err(e) => json!({ "status": "down", "error": e.to_string() }),
+    }
+}
+
+// Проверка того, что кэш заказов инициализирован
+fn check_cache(state: &AppState) -> Value {
+    json!({
+        "status": if state.orders.is_initialized() { "up" } else { "down" },
+        "size": state.orders.len(),
+        "capacity": state.orders.capacity(),
+    })
+}
@@ -0,0 +1,183 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    response::IntoResponse,
+    middleware,
+    routing::{get, post, put},
+    http::StatusCode,
+    Router,
+};
+use std::sync::Arc;
+
+use log::{info, error};
+
+pub mod model; // Модуль, содержащий определения моделей данных
+use model::Order;
+
+pub mod db; // Модуль для работы с базой данных
+use db::AddOrderOutcome;
+
+pub mod error; // Модуль ошибок приложения
+use error::Error;
+
+pub mod repository; // Модуль хранилища заказов
+use repository::OrderRepository;
+
+pub mod memory; // Модуль хранилища заказов в памяти
+
+pub mod sqlite; // Модуль хранилища заказов в SQLite
+
+pub mod cache; // Модуль кэша заказов
+use cache::OrderCache;
+
+pub mod migrate; // Модуль миграций схемы базы данных
+
+pub mod consumer; // Модуль приема заказов из очереди сообщений
+
+pub mod dead_letter; // Модуль хранения отклоненных сообщений с заказами
+
+pub mod orders; // Модуль API списка, изменения и удаления заказов
+
+pub mod bulk; // Модуль массовой загрузки заказов
+
+pub mod validation; // Модуль проверки бизнес-правил заказов
+use validation::ValidationConfig;
+
+pub mod nats; // Модуль подключения к NATS JetStream
+
+pub mod pool; // Модуль пула соединений с базой данных
+use pool::DatabaseHealth;
+
+pub mod metrics; // Модуль метрик Prometheus
+
+pub mod health; // Модуль проверок работоспособности и готовности
+
+pub mod shutdown; // Модуль корректного завершения работы
+
+pub mod cli; // Модуль для обработки командной строки
+use cli::IngestMode;
+
+pub mod config; // Модуль файла конфигурации
+
+pub mod tls; // Модуль TLS для HTTPS-сервера и соединений с базой данных
+
+// Состояние приложения: хранилище и кэш заказов хранятся раздельно,
+// поэтому запросы к хранилищу и обращения к кэшу не блокируют друг друга
+#[derive(Clone)]
+pub struct AppState {
+    pub repository: Arc<dyn OrderRepository>, // Хранилище заказов
+    pub orders: Arc<OrderCache>, // Кэш для хранения заказов
+    pub db_health: Arc<DatabaseHealth>, // Состояние подключения к базе данных
+    pub ingest_mode: IngestMode, // Режим обработки повторных заказов
+    pub validation: Arc<ValidationConfig>, // Настройки проверки заказов
+    pub bulk_batch_size: usize, // Количество заказов, записываемых массовой загрузкой в одной транзакции
+}
+
+// Функция для создания маршрутизатора с заданным состоянием
+pub fn create_router(state: AppState) -> Router {
+    metrics::init();
+    Router::new()
+    .route("/add_order", post(create_order)) // Обработка POST-запроса для добавления заказа
+    .route("/get_order/:uid", get(get_order)) // Обработка GET-запроса для получения заказа по UID
+    .route("/orders", get(orders::list)) // Список заказов с фильтрами и курсорной пагинацией
+    .route("/orders/bulk", post(bulk::load)) // Массовая загрузка заказов из JSON-массива или NDJSON
+    .route("/orders/:uid", put(orders::replace).patch(orders::patch).delete(orders::delete)) // Изменение и удаление заказа
+    .route("/orders/:uid/items/:chrt_id/status", get(orders::item_status).post(orders::change_item_status)) // Статус товара и его история
+    .route("/dead_letters", get(dead_letter::list)) // Список отклоненных сообщений
+    .route("/dead_letters/:id", get(dead_letter::get)) // Отклоненное сообщение по ID
+    .route("/dead_letters/:id/replay", post(dead_letter::replay)) // Повторная обработка отклоненного сообщения
+    .route_layer(middleware::from_fn(metrics::track)) // Учет запросов к маршрутам приложения
+    .route("/metrics", get(metrics::metrics)) // Метрики в формате Prometheus
+    .route("/healthz", get(health::healthz)) // Проверка работоспособности процесса
+    .route("/readyz", get(health::readyz)) // Проверка готовности к обработке запросов
+    .with_state(state) // Устанавливаем состояние для маршрутизатора
+}
+
+// Асинхронная функция для создания нового заказа
+async fn create_order(
+    State(state): State<AppState>, // Извлекаем состояние, которое содержит хранилище и кэш заказов
+    body: Bytes, // Извлекаем тело запроса (сохраняется как есть, если заказ будет отклонен)
+) -> Result<impl IntoResponse, Error> { // Функция возвращает ответ, который может быть преобразован в HTTP-ответ
+    // Разбираем заказ из JSON (некорректное тело запроса превращается в ошибку валидации) и добавляем его в хранилище и кэш
+    let result = match serde_json::from_slice::<Order>(&body) {
+        Ok(order) => ingest_order(&state, order, state.ingest_mode).await,
+        Err(e) => Err(e.into()),
+    };
+
+    match result {
+        Ok(order) => { // Если добавление прошло успешно
+            // Форматируем заказ в красивый JSON
+            let pretty_json_order = serde_json::to_string_pretty(&order).unwrap();
+            // Возвращаем статус 200 и данные заказа
+            Ok((StatusCode::OK, pretty_json_order))
+        }
+        Err(e) => { // В случае ошибки при добавлении заказа
+            error!("Failed to add order: {:?}", e); // Логируем ошибку
+            // Сохраняем отклоненный заказ для повторной обработки
+            dead_letter::store(&state, &body, &e, "http").await;
+            // Возвращаем ошибку, статус ответа определяется ее типом
+            Err(e)
+        }
+    }
+}
+
+// Асинхронная функция для добавления заказа в хранилище и кэш (общая для HTTP и очереди сообщений).
+// Возвращает добавленный заказ, а для повтора в идемпотентном режиме - сохраненный ранее
+async fn ingest_order(state: &AppState, order: Order, ingest_mode: IngestMode) -> Result<Order, Error> {
+    // Проверяем бизнес-правила заказа до обращения к хранилищу
+    validation::validate(&order, &state.validation)?;
+
+    // Добавляем заказ в хранилище
+    let order = match state.repository.add_order(&order).await? {
+        // Новый заказ возвращается как есть
+        AddOrderOutcome::Created => order,
+        // Повтор в строгом режиме отклоняется
+        AddOrderOutcome::Duplicate if ingest_mode == IngestMode::Strict => {
+            return Err(Error::order_conflict(&order.order_uid));
+        }
+        // Повтор в идемпотентном режиме возвращает сохраненный ранее заказ
+        AddOrderOutcome::Duplicate => state.repository.get_order(&order.order_uid).await?,
+    };
+
+    // Сохраняем заказ в кэше
+    state.orders.put(order.clone());
+    Ok(order)
+}
+
+// Асинхронная функция для получения заказа по его UID
+async fn get_order(
+    Path(id): Path<String>, // Извлекаем UID заказа из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние, которое содержит хранилище и кэш заказов
+) -> Result<impl IntoResponse, Error> { // Функция возвращает ответ, который может быть преобразован в HTTP-ответ
+    // Проверяем, есть ли заказ в кэше
+    match state.orders.get(&id) {
+        Some(order) => { // Если заказ найден в кэше
+            info!("Order {:?} found in cache", id); // Логируем, что заказ найден в кэше
+            // Форматируем заказ в красивый JSON
+            let pretty_json_orders = serde_json::to_string_pretty(&*order).unwrap();
+            // Возвращаем статус 200 и данные заказа
+            Ok((StatusCode::OK, pretty_json_orders))
+        },
+        None => { // Если заказ не найден в кэше
+            // Пытаемся получить заказ из хранилища
+            match state.repository.get_order(&id).await {
+                Ok(order) => { // Если заказ успешно получен из хранилища
+                    // Форматируем заказ в красивый JSON
+                    let pretty_json_order = serde_json::to_string_pretty(&order).unwrap();
+                    // Сохраняем заказ в кэше
+                    state.orders.put(order);
+                    // Возвращаем статус 200 и данные заказа
+                    Ok((StatusCode::OK, pretty_json_order))
+                }
+                Err(e) => { // В случае ошибки при получении заказа из хранилища
+                    // Отсутствие заказа - штатная ситуация, внутренние ошибки логируются при формировании ответа
+                    if let Error::NotFound { .. } = e {
+                        info!("Order {:?} not found", id);
+                    }
+                    // Возвращаем ошибку, статус ответа определяется ее типом
+                    Err(e)
+                }
+            }
+        },
+    }
+}
@@ -1,172 +1,270 @@
-use axum::{
-    extract::{Json, Path, State},
-    response::IntoResponse,
-    routing::{get, post},
-    http::StatusCode,
-    Router,
-};
-use clap::Parser;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tokio_postgres::{NoTls, Client};
+use std::time::{Duration, Instant};
 
-use dotenv::dotenv;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_util::task::TaskTracker;
 
-use log::{info, error};
+use dotenv::dotenv;
 
-use serde_json::json;
+use log::{info, warn, error};
 
-use lru::LruCache;
 use std::num::NonZeroUsize;
 
-mod model; // Модуль, содержащий определения моделей данных
-use model::Order;
-
-mod db; // Модуль для работы с базой данных
-
-mod cli; // Модуль для обработки командной строки
-use cli::CliArgs;
-
-// Структура для хранения клиента базы данных и кэша заказов
-struct ClientAndCache {
-    pub client: Client, // Клиент для подключения к базе данных
-    pub orders: LruCache<String, Order>, // Кэш для хранения заказов
-}
-
-// Тип для блокировки доступа к ClientAndCache
-type ClientAndCacheLock = Arc<RwLock<ClientAndCache>>;
+use rust_project_l0::{cache, cli, config, consumer, create_router, migrate, nats, pool, shutdown, tls, AppState};
+use rust_project_l0::cache::OrderCache;
+use rust_project_l0::cli::{CliArgs, Command, ConfigAction, Database, MigrateAction};
+use rust_project_l0::migrate::MigrationStatus;
+use rust_project_l0::pool::{DatabaseHealth, Pool};
+use rust_project_l0::repository::{OrderRepository, PostgresRepository};
+use rust_project_l0::sqlite::SqliteRepository;
+use rust_project_l0::validation::ValidationConfig;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok(); // Загружаем переменные окружения из .env файла
-    let args = CliArgs::parse(); // Парсим аргументы командной строки
+    tls::install_crypto_provider(); // Криптопровайдер для HTTPS и TLS-соединений с базой данных
+    let args = cli::parse(); // Парсим аргументы командной строки, переменные окружения и файл конфигурации
+
+    // Выводим итоговую конфигурацию, если указана подкоманда config check
+    if let Some(Command::Config { action: ConfigAction::Check }) = args.command {
+        print!("{}", toml::to_string_pretty(&config::effective(&args)).expect("Failed to serialize config"));
+        return;
+    }
 
     // Инициализация логирования, если указано в аргументах
     if args.log {
-        log4rs::init_file("log4rs.yaml", Default::default()).unwrap();
+        log4rs::init_file(&args.log_config, Default::default()).unwrap();
+    }
+
+    // Парсим адрес сервера и базу данных из аргументов
+    let (server_address, database) = cli::parse_urls(&args);
+
+    // Выполняем подкоманду, если она указана
+    if let Some(Command::Migrate { action }) = &args.command {
+        run_migrate(action, &database, &args).await;
+        return;
     }
 
-    // Парсим адрес сервера и URL базы данных из аргументов
-    let (server_address, database_url) = cli::parse_urls(&args);
     // Запускаем соединение с базой данных и сервер
-    start_connection(server_address, database_url, args.cache_size).await;
+    start_connection(&args, server_address, database).await;
+}
+
+// Асинхронная функция для выполнения подкоманды migrate
+async fn run_migrate(action: &MigrateAction, database: &Database, args: &CliArgs) {
+    let result = match database {
+        Database::Postgres(database_url) => {
+            let mut client = pool::connect(database_url, &cli::parse_db_tls(args))
+            .await
+            .expect("Failed to connect to the database");
+
+            match action {
+                MigrateAction::Up => migrate::up(&mut client).await.map(print_applied),
+                MigrateAction::Down { steps } => migrate::down(&mut client, *steps).await.map(print_reverted),
+                MigrateAction::Status => migrate::status(&client).await.map(print_status),
+            }
+        }
+        Database::Sqlite(path) => {
+            let repository = SqliteRepository::open(path).expect("Failed to open the database");
+
+            match action {
+                MigrateAction::Up => repository.migrate_up().await.map(print_applied),
+                MigrateAction::Down { steps } => repository.migrate_down(*steps).await.map(print_reverted),
+                MigrateAction::Status => repository.migration_status().await.map(print_status),
+            }
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Migration failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_applied(applied: Vec<i64>) {
+    println!("Applied {} migration(s): {:?}", applied.len(), applied);
+}
+
+fn print_reverted(reverted: Vec<i64>) {
+    println!("Reverted {} migration(s): {:?}", reverted.len(), reverted);
+}
+
+fn print_status(statuses: Vec<MigrationStatus>) {
+    for status in statuses {
+        println!(
+            "{} {:<20} {}",
+            status.migration.version,
+            status.migration.description,
+            status.applied_on.as_deref().unwrap_or("pending"),
+        );
+    }
 }
 
-// Функция для создания маршрутизатора с заданным состоянием
-fn create_router(state: ClientAndCacheLock) -> Router {
-    Router::new()
-    .route("/add_order", post(create_order)) // Обработка POST-запроса для добавления заказа
-    .route("/get_order/:uid", get(get_order)) // Обработка GET-запроса для получения заказа по UID
-    .with_state(state) // Устанавливаем состояние для маршрутизатора
+// Соединения с PostgreSQL, которые закрываются при завершении работы
+struct Postgres {
+    pool: Pool, // Пул соединений
+    connections: TaskTracker, // Фоновые задачи соединений
+    reconnect: JoinHandle<()>, // Задача восстановления соединения
+}
+
+// Асинхронная функция для подключения к PostgreSQL
+async fn start_postgres(args: &CliArgs, database_url: String, db_health: Arc<DatabaseHealth>) -> Postgres {
+    // Создаем пул соединений с базой данных, повторяя попытки, пока база данных недоступна
+    let pool_config = cli::parse_pool_config(args);
+    let connections = TaskTracker::new();
+    let pool = match pool::create_pool(database_url, &pool_config, db_health.clone(), connections.clone()).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Failed to connect to the database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Восстанавливаем соединение с базой данных в фоне при его потере
+    let reconnect = tokio::spawn(pool::reconnect(pool.clone(), db_health, pool_config.reconnect_max_delay));
+
+    // Применяем непримененные миграции, если это указано в аргументах
+    if args.auto_migrate {
+        let mut connection = pool.get().await.expect("Failed to connect to the database");
+        let applied = migrate::up(&mut connection.client).await.expect("Failed to apply migrations");
+        info!("Applied {} migration(s)", applied.len());
+    }
+
+    Postgres { pool, connections, reconnect }
+}
+
+// Асинхронная функция для открытия базы данных SQLite
+async fn start_sqlite(args: &CliArgs, path: &str) -> SqliteRepository {
+    let repository = match SqliteRepository::open(path) {
+        Ok(repository) => repository,
+        Err(e) => {
+            error!("Failed to open the database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Применяем непримененные миграции, если это указано в аргументах
+    if args.auto_migrate {
+        let applied = repository.migrate_up().await.expect("Failed to apply migrations");
+        info!("Applied {} migration(s)", applied.len());
+    }
+
+    repository
 }
 
 // Асинхронная функция для запуска соединения с базой данных и сервера
-async fn start_connection(server_address: String, database_url: String, cache_size: usize) {
+async fn start_connection(args: &CliArgs, server_address: String, database: Database) {
     info!("Starting server..."); // Логируем запуск сервера
 
-    // Подключаемся к базе данных
-    let (client, connection) = tokio_postgres::connect(&database_url, NoTls)
-    .await
-    .expect("Failed to connect to the database");
-    
-    // Запускаем задачу для обработки соединения с базой данных
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("Connection error: {}", e); // Логируем ошибку соединения
+    // Открываем хранилище заказов в выбранной базе данных
+    let db_health = Arc::new(DatabaseHealth::new());
+    let (repository, postgres): (Arc<dyn OrderRepository>, Option<Postgres>) = match database {
+        Database::Postgres(database_url) => {
+            let postgres = start_postgres(args, database_url, db_health.clone()).await;
+            (Arc::new(PostgresRepository::new(postgres.pool.clone())), Some(postgres))
+        }
+        Database::Sqlite(path) => (Arc::new(start_sqlite(args, &path).await), None),
+    };
+
+    // Состояние приложения с хранилищем заказов и кэшем
+    let state = AppState {
+        repository,
+        orders: Arc::new(OrderCache::new(
+            NonZeroUsize::new(args.cache_size).expect("Incorrect cache size passed")
+        )),
+        db_health,
+        ingest_mode: args.ingest_mode,
+        validation: Arc::new(ValidationConfig::new(
+            &args.skip_validation,
+            args.validation_phone_format,
+            args.validation_max_amount,
+        )),
+        bulk_batch_size: NonZeroUsize::new(args.bulk_batch_size).expect("Incorrect bulk batch size passed").get(),
+    };
+
+    // Прогреваем кэш самыми новыми заказами, если это указано в аргументах
+    if args.cache_warm_up > 0 {
+        if let Err(e) = cache::warm_up(&state.orders, &*state.repository, args.cache_warm_up).await {
+            error!("Failed to warm up order cache: {:?}", e); // Сервер запускается с пустым кэшем
         }
-    });
+    }
+    state.orders.mark_initialized();
+
+    // Обрабатываем сигналы завершения работы
+    let grace_period = Duration::from_secs(args.shutdown_grace_period);
+    let handle = Handle::new();
+    let (shutdown_tx, shutdown_rx) = watch::channel(None);
+    tokio::spawn(shutdown::listen(handle.clone(), grace_period, shutdown_tx));
 
-    // Создаем маршрутизатор с клиентом и кэшем
-    let app = create_router(Arc::new(RwLock::new(
-        ClientAndCache {
-            client,
-            orders: LruCache::new(NonZeroUsize::new(cache_size).expect("Incorrect cache size passed")),
+    // Запускаем прием заказов из NATS, если указан адрес сервера
+    let consumer = match cli::parse_nats_config(args) {
+        Some(nats_config) => {
+            let nats_consumer = nats::connect(&nats_config)
+            .await
+            .expect("Failed to connect to NATS");
+            Some(tokio::spawn(consumer::run(nats_consumer, state.clone(), shutdown_rx.clone())))
         }
-    )));
+        None => None,
+    };
+
+    // Создаем маршрутизатор с хранилищем и кэшем
+    let app = create_router(state);
 
     // Парсим адрес для сервера
     let addr = server_address.parse().expect("Unable to parse address");
-    info!("Listening on {}", addr); // Логируем адрес, на котором слушает сервер
-
-    // Запускаем сервер
-    axum_server::bind(addr)
-    .serve(app.into_make_service())
-    .await
-    .unwrap();
-}
 
+    // Запускаем сервер, он завершится после обработки запросов, начатых до сигнала завершения.
+    // Если указан сертификат, сервер принимает HTTPS-соединения
+    match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => {
+            let tls_config = RustlsConfig::from_pem_file(cert, key)
+            .await
+            .expect("Failed to load TLS certificate");
+            tokio::spawn(tls::watch_certificate(
+                tls_config.clone(),
+                cert.clone(),
+                key.clone(),
+                Duration::from_secs(args.tls_reload_interval),
+            ));
 
-// Асинхронная функция для создания нового заказа
-async fn create_order(
-    State(state): State<ClientAndCacheLock>, // Извлекаем состояние, которое содержит клиент и кэш заказов
-    Json(order): Json<Order> // Извлекаем данные заказа из JSON
-) -> impl IntoResponse { // Функция возвращает ответ, который может быть преобразован в HTTP-ответ
-    let mut state = state.write().await; // Получаем доступ к состоянию для записи (блокируем для других потоков)
-
-    // Добавляем заказ в базу данных
-    let result = db::add_order(&order, &state.client).await;
-
-    match result {
-        Ok(_) => { // Если добавление прошло успешно
-            // Сохраняем заказ в кэше
-            state.orders.put(order.order_uid.clone(), order.clone());
-            // Форматируем заказ в красивый JSON
-            let pretty_json_order = serde_json::to_string_pretty(&order).unwrap();
-            // Возвращаем статус 200 и данные заказа
-            (StatusCode::OK, pretty_json_order)
+            info!("Listening on https://{}", addr); // Логируем адрес, на котором слушает сервер
+            axum_server::bind_rustls(addr, tls_config)
+            .handle(handle)
+            .serve(app.into_make_service())
+            .await
+            .unwrap();
         }
-        Err(e) => { // В случае ошибки при добавлении заказа
-            // Формируем ответ с ошибкой
-            let error_response = json!({
-                "success": false,
-                "message": e.to_string(), // Сообщение об ошибке
-            });
-            error!("Failed to add order: {:?}", e); // Логируем ошибку
-            // Возвращаем статус 500 и сообщение об ошибке
-            (StatusCode::INTERNAL_SERVER_ERROR, error_response.to_string())
+        _ => {
+            info!("Listening on {}", addr); // Логируем адрес, на котором слушает сервер
+            axum_server::bind(addr)
+            .handle(handle)
+            .serve(app.into_make_service())
+            .await
+            .unwrap();
         }
     }
-}
 
-// Асинхронная функция для получения заказа по его UID
-async fn get_order(
-    Path(id): Path<String>, // Извлекаем UID заказа из пути запроса
-    State(state): State<ClientAndCacheLock>, // Извлекаем состояние, которое содержит клиент и кэш заказов
-) -> impl IntoResponse { // Функция возвращает ответ, который может быть преобразован в HTTP-ответ
-    let mut state = state.write().await; // Получаем доступ к состоянию для записи (блокируем для других потоков)
-
-    // Проверяем, есть ли заказ в кэше
-    match state.orders.get(&id) {
-        Some(order) => { // Если заказ найден в кэше
-            info!("Order {:?} found in cache", id); // Логируем, что заказ найден в кэше
-            // Форматируем заказ в красивый JSON
-            let pretty_json_orders = serde_json::to_string_pretty(&order).unwrap();
-            // Возвращаем статус 200 и данные заказа
-            (StatusCode::OK, pretty_json_orders)
-        },
-        None => { // Если заказ не найден в кэше
-            // Пытаемся получить заказ из базы данных
-            let result = db::get_order_by_uid(&id, &state.client).await;
-            match result {
-                Ok(order) => { // Если заказ успешно получен из базы данных
-                    // Сохраняем заказ в кэше
-                    state.orders.put(order.order_uid.clone(), order.clone());
-                    // Форматируем заказ в красивый JSON
-                    let pretty_json_order = serde_json::to_string_pretty(&order).unwrap();
-                    // Возвращаем статус 200 и данные заказа
-                    (StatusCode::OK, pretty_json_order)
-                }
-                Err(e) => { // В случае ошибки при получении заказа из базы данных
-                    // Формируем ответ с ошибкой
-                    let error_response = json!({
-                        "success": false,
-                        "message": e.to_string(), // Сообщение об ошибке
-                    });
-                    error!("Failed to get order from db: {:?}", e); // Логируем ошибку
-                    // Возвращаем статус 500 и сообщение об ошибке
-                    (StatusCode::INTERNAL_SERVER_ERROR, error_response.to_string())
-                }
-            }
-        },
+    // Ожидаем обработки заказа, полученного из очереди, в пределах оставшегося времени.
+    // По истечении времени задача прерывается и освобождает свою копию состояния с пулом соединений,
+    // иначе закрытие пула ждало бы соединения, которые задача так и не вернет
+    let deadline = shutdown_rx.borrow().unwrap_or_else(Instant::now) + grace_period;
+    if let Some(mut consumer) = consumer {
+        if tokio::time::timeout_at(deadline.into(), &mut consumer).await.is_err() {
+            warn!("Timed out waiting for the order consumer to finish, aborting it");
+            consumer.abort();
+            let _ = consumer.await;
+        }
     }
+
+    // Закрываем соединения с базой данных
+    if let Some(Postgres { pool, connections, reconnect }) = postgres {
+        reconnect.abort();
+        let _ = reconnect.await;
+        pool::close(pool, connections, deadline.saturating_duration_since(Instant::now())).await;
+    }
+    info!("Server stopped");
 }
+
+
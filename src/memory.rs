@@ -0,0 +1,282 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+
+use crate::db::{self, AddOrderOutcome, OrderFilter}; // Фильтры списка и проверка переходов статусов
+use crate::error::Error; // Тип ошибки приложения
+use crate::migrate::{MigrationStatus, MIGRATIONS}; // Миграции схемы базы данных
+use crate::model::{DeadLetter, ItemStatus, ItemStatusChange, Order}; // Модели данных
+use crate::repository::{OrderModifier, OrderRepository}; // Трейт хранилища заказов
+
+// Хранилище заказов в памяти процесса (для тестов и локального запуска без базы данных).
+// Повторяет поведение PostgresRepository: уникальность UID заказа и идентификатора оплаты,
+// порядок сортировки списка, проверку переходов статусов товаров
+#[derive(Default)]
+pub struct MemoryRepository {
+    data: Mutex<Data>, // Данные хранилища, блокировка не удерживается между await
+}
+
+// Данные хранилища в памяти
+#[derive(Default)]
+struct Data {
+    orders: HashMap<String, Order>, // Заказы по UID
+    item_history: HashMap<(String, i64), Vec<ItemStatusChange>>, // История статусов по (UID заказа, chrt_id)
+    dead_letters: BTreeMap<i64, DeadLetter>, // Отклоненные сообщения по ID
+    next_dead_letter_id: i64, // ID последнего добавленного отклоненного сообщения
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Data {
+    // Идентификатор оплаты уникален, как первичный ключ таблицы payment
+    fn check_payment(&self, order: &Order) -> Result<(), Error> {
+        let taken = self.orders.values()
+            .any(|other| other.order_uid != order.order_uid && other.payment.transaction == order.payment.transaction);
+        if !taken {
+            return Ok(());
+        }
+
+        Err(Error::payment_conflict(&order.payment.transaction))
+    }
+
+    // Добавление заказа, повтор UID ничего не меняет
+    fn add(&mut self, order: &Order) -> Result<AddOrderOutcome, Error> {
+        if self.orders.contains_key(&order.order_uid) {
+            return Ok(AddOrderOutcome::Duplicate);
+        }
+        self.check_payment(order)?;
+        let changes = db::item_status_changes(&[], order)?;
+        self.orders.insert(order.order_uid.clone(), order.clone());
+        self.record_history(&order.order_uid, changes);
+        Ok(AddOrderOutcome::Created)
+    }
+
+    // Замена существующего заказа, смена статусов товаров проверяется и записывается в историю
+    fn replace(&mut self, order: &Order) -> Result<(), Error> {
+        let current: Vec<(i64, i32)> = self.orders.get(&order.order_uid)
+            .ok_or_else(|| Error::order_not_found(&order.order_uid))?
+            .items.iter()
+            .map(|item| (item.chrt_id, item.status))
+            .collect();
+        self.check_payment(order)?;
+        let changes = db::item_status_changes(&current, order)?;
+        self.orders.insert(order.order_uid.clone(), order.clone());
+        self.record_history(&order.order_uid, changes);
+        Ok(())
+    }
+
+    // Запись изменений статусов товаров заказа в историю
+    fn record_history(&mut self, order_uid: &str, changes: Vec<db::StatusChange>) {
+        for (chrt_id, old_status, new_status) in changes {
+            self.item_history.entry((order_uid.to_string(), chrt_id)).or_default().push(ItemStatusChange {
+                old_status,
+                new_status,
+                changed_at: now(),
+            });
+        }
+    }
+}
+
+// Текущее время в формате, в котором PostgreSQL возвращает метки времени
+fn now() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S%.6f+00").to_string()
+}
+
+// Разбор границы диапазона дат с округлением до микросекунд, как момент создания заказа
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|date| date.with_timezone(&Utc).round_subsecs(6))
+}
+
+// Соответствие заказа фильтрам списка (без курсора).
+// Заказ с неразобранной датой (None) считается самым ранним, как -infinity в PostgreSQL
+fn matches(order: &Order, filter: &OrderFilter) -> bool {
+    let created = order.created_at();
+    let from = filter.created_from.as_deref().and_then(parse_date);
+    let to = filter.created_to.as_deref().and_then(parse_date);
+
+    filter.customer_id.as_ref().is_none_or(|value| &order.customer_id == value)
+        && filter.track_number.as_ref().is_none_or(|value| &order.track_number == value)
+        && filter.delivery_service.as_ref().is_none_or(|value| &order.delivery_service == value)
+        && filter.payment_provider.as_ref().is_none_or(|value| &order.payment.provider == value)
+        && from.is_none_or(|from| created >= Some(from))
+        && to.is_none_or(|to| created < Some(to))
+        && filter.brand.as_ref().is_none_or(|brand| order.items.iter().any(|item| &item.brand == brand))
+        && filter.nm_id.is_none_or(|nm_id| order.items.iter().any(|item| item.nm_id == nm_id))
+}
+
+#[async_trait]
+impl OrderRepository for MemoryRepository {
+    async fn add_order(&self, order: &Order) -> Result<AddOrderOutcome, Error> {
+        self.data.lock().unwrap().add(order)
+    }
+
+    async fn add_orders(&self, orders: &[Order]) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+        let mut data = self.data.lock().unwrap();
+        Ok(orders.iter().map(|order| data.add(order)).collect())
+    }
+
+    async fn get_order(&self, order_uid: &str) -> Result<Order, Error> {
+        self.data.lock().unwrap().orders.get(order_uid)
+            .cloned()
+            .ok_or_else(|| Error::order_not_found(order_uid))
+    }
+
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, Error> {
+        let data = self.data.lock().unwrap();
+
+        let mut orders: Vec<&Order> = data.orders.values()
+            .filter(|order| matches(order, filter))
+            .filter(|order| match &filter.after {
+                // Курсор: заказы строго после последнего заказа предыдущей страницы в порядке сортировки
+                Some((created_at, order_uid)) => {
+                    let key = (order.created_at(), &order.order_uid);
+                    if filter.descending { key < (*created_at, order_uid) } else { key > (*created_at, order_uid) }
+                }
+                None => true,
+            })
+            .collect();
+
+        // Заказы с неразобранной датой (None) идут раньше остальных, как -infinity в PostgreSQL
+        orders.sort_by_cached_key(|order| (order.created_at(), order.order_uid.clone()));
+        if filter.descending {
+            orders.reverse();
+        }
+
+        Ok(orders.into_iter().take(filter.limit.max(0) as usize).cloned().collect())
+    }
+
+    async fn get_orders(&self, order_uids: &[String]) -> Result<Vec<Order>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(order_uids.iter().filter_map(|order_uid| data.orders.get(order_uid).cloned()).collect())
+    }
+
+    async fn recent_order_uids(&self, limit: i64) -> Result<Vec<String>, Error> {
+        let data = self.data.lock().unwrap();
+
+        // От новых к старым, при равной дате - по UID
+        let mut orders: Vec<&Order> = data.orders.values().collect();
+        orders.sort_by_cached_key(|order| (Reverse(order.created_at()), order.order_uid.clone()));
+
+        Ok(orders.into_iter().take(limit.max(0) as usize).map(|order| order.order_uid.clone()).collect())
+    }
+
+    async fn update_order(&self, order: &Order) -> Result<(), Error> {
+        self.data.lock().unwrap().replace(order)
+    }
+
+    async fn modify_order(&self, order_uid: &str, modify: OrderModifier<'_>) -> Result<Order, Error> {
+        let mut data = self.data.lock().unwrap();
+        let current = data.orders.get(order_uid)
+            .cloned()
+            .ok_or_else(|| Error::order_not_found(order_uid))?;
+
+        let order = modify(current)?;
+        data.replace(&order)?;
+        Ok(order)
+    }
+
+    async fn delete_order(&self, order_uid: &str) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.orders.remove(order_uid).ok_or_else(|| Error::order_not_found(order_uid))?;
+        data.item_history.retain(|(history_order_uid, _), _| history_order_uid != order_uid);
+        Ok(())
+    }
+
+    async fn transition_item_status(&self, order_uid: &str, chrt_id: i64, status: ItemStatus) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        let order = data.orders.get_mut(order_uid).ok_or_else(|| Error::item_not_found(order_uid, chrt_id))?;
+
+        let mut items: Vec<_> = order.items.iter_mut().filter(|item| item.chrt_id == chrt_id).collect();
+        if items.is_empty() {
+            return Err(Error::item_not_found(order_uid, chrt_id));
+        }
+        // Все товары проверяются до изменения, чтобы ошибка не оставила часть товаров в новом статусе
+        for item in &items {
+            db::check_item_transition(chrt_id, item.status, status)?;
+        }
+
+        let mut changes = Vec::with_capacity(items.len());
+        for item in items.iter_mut() {
+            changes.push(ItemStatusChange {
+                old_status: Some(item.status),
+                new_status: status.code(),
+                changed_at: now(),
+            });
+            item.status = status.code();
+        }
+
+        data.item_history.entry((order_uid.to_string(), chrt_id)).or_default().extend(changes);
+        Ok(())
+    }
+
+    async fn item_status_history(&self, order_uid: &str, chrt_id: i64) -> Result<(i32, Vec<ItemStatusChange>), Error> {
+        let data = self.data.lock().unwrap();
+        let status = data.orders.get(order_uid)
+            .and_then(|order| order.items.iter().find(|item| item.chrt_id == chrt_id))
+            .map(|item| item.status)
+            .ok_or_else(|| Error::item_not_found(order_uid, chrt_id))?;
+
+        let history = data.item_history.get(&(order_uid.to_string(), chrt_id)).cloned().unwrap_or_default();
+        Ok((status, history))
+    }
+
+    async fn insert_dead_letter(&self, payload: &str, reason: &str, source: &str) -> Result<i64, Error> {
+        let mut data = self.data.lock().unwrap();
+        data.next_dead_letter_id += 1;
+        let id = data.next_dead_letter_id;
+        data.dead_letters.insert(id, DeadLetter {
+            id,
+            payload: payload.to_string(),
+            reason: reason.to_string(),
+            source: source.to_string(),
+            created_at: now(),
+        });
+        Ok(id)
+    }
+
+    async fn list_dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<DeadLetter>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data.dead_letters.values()
+            .rev()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_dead_letter(&self, id: i64) -> Result<DeadLetter, Error> {
+        self.data.lock().unwrap().dead_letters.get(&id)
+            .cloned()
+            .ok_or_else(|| Error::dead_letter_not_found(id))
+    }
+
+    async fn update_dead_letter_reason(&self, id: i64, reason: &str) -> Result<(), Error> {
+        if let Some(dead_letter) = self.data.lock().unwrap().dead_letters.get_mut(&id) {
+            dead_letter.reason = reason.to_string();
+        }
+        Ok(())
+    }
+
+    async fn delete_dead_letter(&self, id: i64) -> Result<(), Error> {
+        self.data.lock().unwrap().dead_letters.remove(&id);
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Схема в памяти всегда соответствует последней миграции
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, Error> {
+        Ok(MIGRATIONS.iter()
+            .map(|migration| MigrationStatus { migration, applied_on: Some(String::new()) })
+            .collect())
+    }
+}
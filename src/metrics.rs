@@ -0,0 +1,117 @@
+use std::sync::LazyLock;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+// Реестр метрик приложения
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+// Регистрация метрики в реестре
+fn register<M: prometheus::core::Collector + Clone + 'static>(metric: M) -> M {
+    REGISTRY.register(Box::new(metric.clone())).expect("Failed to register metric");
+    metric
+}
+
+// Количество HTTP-запросов по методу, маршруту и статусу ответа
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| register(IntCounterVec::new(
+    Opts::new("http_requests_total", "Number of HTTP requests"),
+    &["method", "route", "status"],
+).unwrap()));
+
+// Время обработки HTTP-запросов по методу и маршруту
+static HTTP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| register(HistogramVec::new(
+    HistogramOpts::new("http_request_duration_seconds", "HTTP request latency in seconds"),
+    &["method", "route"],
+).unwrap()));
+
+// Количество обрабатываемых в данный момент HTTP-запросов
+static HTTP_IN_FLIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| register(IntGaugeVec::new(
+    Opts::new("http_requests_in_flight", "Number of HTTP requests being processed"),
+    &["method", "route"],
+).unwrap()));
+
+// Время выполнения функций модуля db
+static DB_QUERY_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| register(HistogramVec::new(
+    HistogramOpts::new("db_query_duration_seconds", "Database function latency in seconds"),
+    &["function"],
+).unwrap()));
+
+// Обращения к кэшу заказов
+pub static CACHE_HITS: LazyLock<IntCounter> = LazyLock::new(|| register(
+    IntCounter::new("order_cache_hits_total", "Number of order cache hits").unwrap()
+));
+pub static CACHE_MISSES: LazyLock<IntCounter> = LazyLock::new(|| register(
+    IntCounter::new("order_cache_misses_total", "Number of order cache misses").unwrap()
+));
+pub static CACHE_EVICTIONS: LazyLock<IntCounter> = LazyLock::new(|| register(
+    IntCounter::new("order_cache_evictions_total", "Number of orders evicted from the cache").unwrap()
+));
+
+// Регистрация всех метрик, чтобы они выдавались с нулевыми значениями еще до первого обращения
+pub fn init() {
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_DURATION);
+    LazyLock::force(&HTTP_IN_FLIGHT);
+    LazyLock::force(&DB_QUERY_DURATION);
+    LazyLock::force(&CACHE_HITS);
+    LazyLock::force(&CACHE_MISSES);
+    LazyLock::force(&CACHE_EVICTIONS);
+}
+
+// Таймер выполнения функции модуля db, время записывается при удалении таймера
+pub fn db_timer(function: &str) -> HistogramTimer {
+    DB_QUERY_DURATION.with_label_values(&[function]).start_timer()
+}
+
+// Учет запроса в http_requests_in_flight: значение уменьшается при удалении, в том числе когда обработка
+// прервана (клиент закрыл соединение или истек срок ожидания при завершении работы)
+struct InFlight(IntGauge);
+
+impl InFlight {
+    fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        InFlight(gauge)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+// Middleware для учета HTTP-запросов. Подключается через route_layer,
+// поэтому маршрут берется из шаблона пути (например /get_order/:uid), а не из самого пути
+pub async fn track(matched_path: Option<MatchedPath>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = matched_path.map(|path| path.as_str().to_string()).unwrap_or_default();
+    let labels = [method.as_str(), route.as_str()];
+
+    let in_flight = InFlight::new(HTTP_IN_FLIGHT.with_label_values(&labels));
+    let timer = HTTP_DURATION.with_label_values(&labels).start_timer();
+
+    let response = next.run(request).await;
+
+    timer.observe_duration();
+    drop(in_flight);
+    HTTP_REQUESTS.with_label_values(&[method.as_str(), route.as_str(), response.status().as_str()]).inc();
+
+    response
+}
+
+// Асинхронная функция для выдачи метрик в текстовом формате Prometheus
+pub async fn metrics() -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    match encoder.encode(&REGISTRY.gather(), &mut buffer) {
+        Ok(()) => (StatusCode::OK, [(header::CONTENT_TYPE, encoder.format_type().to_string())], buffer).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,155 @@
+use tokio_postgres::GenericClient; // Общий трейт для клиента и транзакции
+use log::info; // Макрос для логирования информации
+
+use crate::error::Error; // Тип ошибки приложения
+
+// Миграция схемы базы данных, SQL встраивается в бинарный файл при сборке
+pub struct Migration {
+    pub version: i64, // Версия (время создания миграции)
+    pub description: &'static str, // Описание миграции
+    pub(crate) up: &'static str, // SQL для применения миграции
+    pub(crate) down: &'static str, // SQL для отката миграции
+}
+
+// Встраивание миграции из каталога migrations (или его подкаталога) по версии и описанию
+macro_rules! migration {
+    ($version:literal, $description:literal) => {
+        migration!("", $version, $description)
+    };
+    ($dir:literal, $version:literal, $description:literal) => {
+        Migration {
+            version: $version,
+            description: $description,
+            up: include_str!(concat!("../migrations/", $dir, $version, "_", $description, ".up.sql")),
+            down: include_str!(concat!("../migrations/", $dir, $version, "_", $description, ".down.sql")),
+        }
+    };
+}
+
+// Все миграции в порядке применения
+pub static MIGRATIONS: &[Migration] = &[
+    migration!(20240926172737, "create_order"),
+    migration!(20241010120000, "item_per_order"),
+    migration!(20241020120000, "dead_letter"),
+    migration!(20241101120000, "item_status_history"),
+    migration!(20241201120000, "order_created_at"),
+];
+
+// Миграции схемы SQLite (см. модуль sqlite), применяются так же по порядку
+pub static SQLITE_MIGRATIONS: &[Migration] = &[
+    migration!("sqlite/", 20241115120000, "create_schema"),
+    migration!("sqlite/", 20241201120000, "order_created_at"),
+];
+
+// Ключ advisory-блокировки, не позволяющей нескольким экземплярам мигрировать одновременно
+const MIGRATION_LOCK_KEY: i64 = 0x006d_6967_7261_7465;
+
+// Состояние миграции в базе данных
+pub struct MigrationStatus {
+    pub migration: &'static Migration, // Миграция
+    pub applied_on: Option<String>, // Время применения, если миграция применена
+}
+
+// Создание таблицы, в которой хранятся версии примененных миграций
+async fn ensure_schema_table(client: &impl GenericClient) -> Result<(), Error> {
+    client.batch_execute(r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            description VARCHAR(255) NOT NULL,
+            applied_on TIMESTAMPTZ NOT NULL DEFAULT now()
+        )
+    "#).await?;
+    Ok(())
+}
+
+// Получение состояния всех известных миграций. Запрос только читает схему (используется и в /readyz,
+// где у роли сервиса может не быть прав на DDL): если таблицы версий нет, все миграции считаются непримененными
+pub async fn status(client: &impl GenericClient) -> Result<Vec<MigrationStatus>, Error> {
+    let exists: bool = client.query_one("SELECT to_regclass('schema_migrations') IS NOT NULL", &[]).await?.get(0);
+    let rows = if exists {
+        client.query(
+            "SELECT version, to_char(applied_on, 'YYYY-MM-DD\"T\"HH24:MI:SSOF') FROM schema_migrations",
+            &[],
+        ).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MIGRATIONS.iter()
+        .map(|migration| MigrationStatus {
+            migration,
+            applied_on: rows.iter()
+                .find(|row| row.get::<_, i64>(0) == migration.version)
+                .map(|row| row.get(1)),
+        })
+        .collect())
+}
+
+// Применение всех непримененных миграций, каждая в своей транзакции.
+// Возвращает версии примененных миграций
+pub async fn up<C: GenericClient>(client: &mut C) -> Result<Vec<i64>, Error> {
+    client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    let result = apply_pending(client).await;
+    client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    result
+}
+
+async fn apply_pending<C: GenericClient>(client: &mut C) -> Result<Vec<i64>, Error> {
+    ensure_schema_table(client).await?;
+    let mut applied = Vec::new();
+
+    for status in status(client).await? {
+        if status.applied_on.is_some() {
+            continue;
+        }
+
+        let migration = status.migration;
+        info!("Applying migration {} {}", migration.version, migration.description);
+
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(migration.up).await?;
+        transaction.execute(
+            "INSERT INTO schema_migrations (version, description) VALUES ($1, $2)",
+            &[&migration.version, &migration.description],
+        ).await?;
+        transaction.commit().await?;
+
+        info!("Successfully applied migration {}", migration.version);
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+// Откат последних steps примененных миграций в обратном порядке.
+// Возвращает версии откаченных миграций
+pub async fn down<C: GenericClient>(client: &mut C, steps: usize) -> Result<Vec<i64>, Error> {
+    client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    let result = revert_applied(client, steps).await;
+    client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    result
+}
+
+async fn revert_applied<C: GenericClient>(client: &mut C, steps: usize) -> Result<Vec<i64>, Error> {
+    let mut reverted = Vec::new();
+
+    let applied: Vec<&Migration> = status(client).await?
+        .into_iter()
+        .filter(|status| status.applied_on.is_some())
+        .map(|status| status.migration)
+        .collect();
+
+    for migration in applied.into_iter().rev().take(steps) {
+        info!("Reverting migration {} {}", migration.version, migration.description);
+
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(migration.down).await?;
+        transaction.execute("DELETE FROM schema_migrations WHERE version = $1", &[&migration.version]).await?;
+        transaction.commit().await?;
+
+        info!("Successfully reverted migration {}", migration.version);
+        reverted.push(migration.version);
+    }
+
+    Ok(reverted)
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, SubsecRound, Utc};
 use serde::{Serialize, Deserialize};
 
 //  Структура информации о доставке
@@ -40,7 +41,66 @@ pub struct Item {
     pub total_price: i32,
     pub nm_id: i64,
     pub brand: String,
-    pub status: i32,
+    pub status: i32, // Код статуса товара (см. ItemStatus)
+}
+
+// Статусы жизненного цикла товара в заказе
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Accepted, // 202 - заказ принят
+    Assembled, // 203 - товар собран
+    Shipped, // 204 - товар передан в доставку
+    Delivered, // 205 - товар доставлен
+    Returned, // 206 - товар возвращен
+    Cancelled, // 207 - товар отменен
+}
+
+impl ItemStatus {
+    pub const ALL: [ItemStatus; 6] = [
+        ItemStatus::Accepted,
+        ItemStatus::Assembled,
+        ItemStatus::Shipped,
+        ItemStatus::Delivered,
+        ItemStatus::Returned,
+        ItemStatus::Cancelled,
+    ];
+
+    // Код статуса, хранящийся в Item.status
+    pub fn code(self) -> i32 {
+        match self {
+            ItemStatus::Accepted => 202,
+            ItemStatus::Assembled => 203,
+            ItemStatus::Shipped => 204,
+            ItemStatus::Delivered => 205,
+            ItemStatus::Returned => 206,
+            ItemStatus::Cancelled => 207,
+        }
+    }
+
+    // Статус по коду (None для неизвестного кода)
+    pub fn from_code(code: i32) -> Option<ItemStatus> {
+        ItemStatus::ALL.into_iter().find(|status| status.code() == code)
+    }
+
+    // Статусы, в которые разрешен переход из текущего
+    pub fn next(self) -> &'static [ItemStatus] {
+        match self {
+            ItemStatus::Accepted => &[ItemStatus::Assembled, ItemStatus::Cancelled],
+            ItemStatus::Assembled => &[ItemStatus::Shipped, ItemStatus::Cancelled],
+            ItemStatus::Shipped => &[ItemStatus::Delivered, ItemStatus::Returned],
+            ItemStatus::Delivered => &[ItemStatus::Returned],
+            ItemStatus::Returned | ItemStatus::Cancelled => &[],
+        }
+    }
+}
+
+// Структура записи истории изменения статуса товара
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ItemStatusChange {
+    pub old_status: Option<i32>,
+    pub new_status: i32,
+    pub changed_at: String,
 }
 
 // Структора информации о заказе
@@ -61,3 +121,23 @@ pub struct Order {
     pub date_created: String,
     pub oof_shard: String,
 }
+
+impl Order {
+    // Момент создания заказа (date_created) в UTC с точностью до микросекунд, как его хранит PostgreSQL.
+    // По нему сортируется список заказов, None - если date_created не в формате RFC 3339
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.date_created)
+            .ok()
+            .map(|date| date.with_timezone(&Utc).round_subsecs(6))
+    }
+}
+
+// Структура отклоненного сообщения с заказом (dead letter)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub payload: String,
+    pub reason: String,
+    pub source: String,
+    pub created_at: String,
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use async_nats::jetstream::{self, consumer::{pull, AckPolicy, PullConsumer}, stream, AckKind};
+use futures::StreamExt;
+use log::info; // Макрос для логирования информации
+
+use crate::consumer::{Consumer, Message}; // Трейты источника сообщений
+use crate::error::Error; // Тип ошибки приложения
+
+// Настройки подключения к NATS JetStream
+pub struct NatsConfig {
+    pub url: String, // Адрес сервера NATS
+    pub stream: String, // Имя потока JetStream
+    pub subject: String, // Subject, в который публикуются заказы
+    pub consumer: String, // Имя durable-консьюмера
+}
+
+// Источник сообщений из NATS JetStream (pull-консьюмер с явным подтверждением)
+pub struct NatsConsumer {
+    messages: pull::Stream,
+}
+
+// Преобразование ошибки клиента NATS в ошибку приложения
+fn backend<E: std::error::Error + Send + Sync + 'static>(e: E) -> Error {
+    Error::Backend(Box::new(e))
+}
+
+// Подключение к NATS и подписка на subject через durable-консьюмер
+pub async fn connect(config: &NatsConfig) -> Result<NatsConsumer, Error> {
+    info!("Connecting to NATS at {} (stream {}, subject {})", config.url, config.stream, config.subject);
+
+    let client = async_nats::connect(&config.url).await.map_err(backend)?;
+    let context = jetstream::new(client);
+
+    let stream = context.get_or_create_stream(stream::Config {
+        name: config.stream.clone(),
+        subjects: vec![config.subject.clone()],
+        ..Default::default()
+    }).await.map_err(backend)?;
+
+    let consumer: PullConsumer = stream.get_or_create_consumer(&config.consumer, pull::Config {
+        durable_name: Some(config.consumer.clone()),
+        filter_subject: config.subject.clone(),
+        ack_policy: AckPolicy::Explicit,
+        ..Default::default()
+    }).await.map_err(backend)?;
+
+    let messages = consumer.messages().await.map_err(backend)?;
+
+    info!("Connected to NATS, consuming orders from {}", config.subject);
+    Ok(NatsConsumer { messages })
+}
+
+impl Consumer for NatsConsumer {
+    type Message = jetstream::Message;
+
+    const SOURCE: &'static str = "nats";
+
+    async fn next(&mut self) -> Result<Option<jetstream::Message>, Error> {
+        self.messages.next().await.transpose().map_err(backend)
+    }
+}
+
+impl Message for jetstream::Message {
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    async fn ack(self) -> Result<(), Error> {
+        jetstream::Message::ack(&self).await.map_err(Error::Backend)
+    }
+
+    async fn nack(self, delay: Duration) -> Result<(), Error> {
+        jetstream::Message::ack_with(&self, AckKind::Nak(Some(delay))).await.map_err(Error::Backend)
+    }
+}
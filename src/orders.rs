@@ -0,0 +1,261 @@
+use axum::{
+    extract::{rejection::{JsonRejection, PathRejection, QueryRejection}, Json, Path, Query, State},
+    response::IntoResponse,
+    http::StatusCode,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use log::info; // Макрос для логирования информации
+
+use crate::db::{self, OrderFilter}; // Модуль для работы с базой данных
+use crate::error::Error; // Тип ошибки приложения
+use crate::model::{ItemStatus, Order}; // Модели заказа и статуса товара
+use crate::validation; // Проверка бизнес-правил заказов
+use crate::AppState; // Состояние приложения
+
+// Количество заказов на странице по умолчанию и максимальное
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 1000;
+
+// Направление сортировки по моменту создания заказа
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc, // От старых к новым
+    #[default]
+    Desc, // От новых к старым
+}
+
+// Параметры запроса списка заказов
+#[derive(Deserialize)]
+pub struct ListParams {
+    customer_id: Option<String>,
+    track_number: Option<String>,
+    delivery_service: Option<String>,
+    #[serde(alias = "payment.provider")]
+    payment_provider: Option<String>,
+    created_from: Option<String>, // Начало диапазона date_created (RFC 3339, включительно)
+    created_to: Option<String>, // Конец диапазона date_created (RFC 3339, не включительно)
+    brand: Option<String>, // Бренд товара
+    nm_id: Option<i64>, // nm_id товара
+    cursor: Option<String>, // Курсор следующей страницы из предыдущего ответа
+    limit: Option<i64>, // Количество заказов на странице
+    #[serde(default)]
+    sort: SortOrder, // Направление сортировки
+}
+
+// Ошибка некорректного параметра запроса
+pub(crate) fn invalid_param(param: &str, reason: impl Into<String>) -> Error {
+    Error::Validation {
+        message: format!("Invalid query parameter {}", param),
+        details: json!({ "param": param, "reason": reason.into() }),
+    }
+}
+
+// Курсор кодирует момент создания (Order::created_at в UTC) и order_uid последнего заказа страницы:
+// заказы сортируются по моменту создания, а не по строке date_created
+fn encode_cursor(order: &Order) -> String {
+    URL_SAFE_NO_PAD.encode(json!([order.created_at().map(db::timestamp), order.order_uid]).to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Result<(Option<DateTime<Utc>>, String), Error> {
+    let (created_at, order_uid): (Option<String>, String) = URL_SAFE_NO_PAD.decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| invalid_param("cursor", "malformed cursor"))?;
+    let created_at = created_at
+        .map(|created_at| DateTime::parse_from_rfc3339(&created_at).map(|date| date.with_timezone(&Utc)))
+        .transpose()
+        .map_err(|_| invalid_param("cursor", "malformed cursor"))?;
+    Ok((created_at, order_uid))
+}
+
+// Проверка границы диапазона дат
+fn check_date(param: &str, value: &Option<String>) -> Result<(), Error> {
+    match value {
+        Some(value) if DateTime::parse_from_rfc3339(value).is_err() => {
+            Err(invalid_param(param, format!("{:?} is not an RFC 3339 date-time", value)))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Асинхронная функция для получения списка заказов по фильтрам с курсорной пагинацией
+pub async fn list(
+    State(state): State<AppState>, // Извлекаем состояние приложения
+    params: Result<Query<ListParams>, QueryRejection>, // Извлекаем фильтры и параметры страницы
+) -> Result<impl IntoResponse, Error> {
+    let Query(params) = params?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(invalid_param("limit", format!("must be between 1 and {}", MAX_LIMIT)));
+    }
+    check_date("created_from", &params.created_from)?;
+    check_date("created_to", &params.created_to)?;
+
+    let filter = OrderFilter {
+        customer_id: params.customer_id,
+        track_number: params.track_number,
+        delivery_service: params.delivery_service,
+        payment_provider: params.payment_provider,
+        created_from: params.created_from,
+        created_to: params.created_to,
+        brand: params.brand,
+        nm_id: params.nm_id,
+        after: params.cursor.as_deref().map(decode_cursor).transpose()?,
+        descending: params.sort == SortOrder::Desc,
+        // Запрашиваем на один заказ больше, чтобы узнать, есть ли следующая страница
+        limit: limit + 1,
+    };
+
+    let mut orders = state.repository.list_orders(&filter).await?;
+
+    let next_cursor = if orders.len() as i64 > limit {
+        orders.truncate(limit as usize);
+        orders.last().map(encode_cursor)
+    } else {
+        None
+    };
+
+    Ok(Json(json!({
+        "orders": orders,
+        "next_cursor": next_cursor,
+    })))
+}
+
+// UID заказа в теле запроса должен совпадать с UID в пути
+fn check_order_uid(order: &Order, order_uid: &str) -> Result<(), Error> {
+    if order.order_uid == order_uid {
+        Ok(())
+    } else {
+        Err(Error::Validation {
+            message: "order_uid cannot be changed".to_string(),
+            details: json!({ "path": order_uid, "body": order.order_uid }),
+        })
+    }
+}
+
+// Ответ с измененным заказом, заказ в кэше обновляется
+fn updated(state: &AppState, order: Order) -> impl IntoResponse {
+    info!("Order {:?} updated", order.order_uid);
+    let pretty_json_order = serde_json::to_string_pretty(&order).unwrap();
+    state.orders.put(order);
+    (StatusCode::OK, pretty_json_order)
+}
+
+// Асинхронная функция для полной замены заказа (PUT)
+pub async fn replace(
+    Path(order_uid): Path<String>, // Извлекаем UID заказа из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+    payload: Result<Json<Order>, JsonRejection>, // Извлекаем новые данные заказа из JSON
+) -> Result<impl IntoResponse, Error> {
+    let Json(order) = payload?;
+    check_order_uid(&order, &order_uid)?;
+    validation::validate(&order, &state.validation)?;
+
+    state.repository.update_order(&order).await?;
+
+    Ok(updated(&state, order))
+}
+
+// Асинхронная функция для частичного изменения заказа (PATCH, JSON Merge Patch - RFC 7396).
+// Чтение текущего заказа и запись изменений выполняются в хранилище атомарно
+pub async fn patch(
+    Path(order_uid): Path<String>, // Извлекаем UID заказа из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+    payload: Result<Json<Value>, JsonRejection>, // Извлекаем изменения заказа из JSON
+) -> Result<impl IntoResponse, Error> {
+    let Json(patch) = payload?;
+
+    // Применяем изменения к текущему заказу
+    let modify = |current: Order| -> Result<Order, Error> {
+        let mut document = serde_json::to_value(&current).unwrap();
+        merge_patch(&mut document, &patch);
+        let order: Order = serde_json::from_value(document)?;
+
+        check_order_uid(&order, &order_uid)?;
+        validation::validate(&order, &state.validation)?;
+        Ok(order)
+    };
+    let order = state.repository.modify_order(&order_uid, &modify).await?;
+
+    Ok(updated(&state, order))
+}
+
+// Асинхронная функция для удаления заказа, заказ удаляется и из кэша
+pub async fn delete(
+    Path(order_uid): Path<String>, // Извлекаем UID заказа из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+) -> Result<impl IntoResponse, Error> {
+    state.repository.delete_order(&order_uid).await?;
+    state.orders.remove(&order_uid);
+
+    info!("Order {:?} deleted", order_uid);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Применение JSON Merge Patch: null удаляет поле, объекты объединяются рекурсивно,
+// остальные значения (в том числе массивы) заменяются целиком
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// Тело запроса изменения статуса товара
+#[derive(Deserialize)]
+pub struct StatusChange {
+    status: ItemStatus, // Новый статус
+}
+
+// Асинхронная функция для получения текущего статуса товара и истории его изменений
+pub async fn item_status(
+    path: Result<Path<(String, i64)>, PathRejection>, // Извлекаем UID заказа и chrt_id товара из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+) -> Result<impl IntoResponse, Error> {
+    let Path((order_uid, chrt_id)) = path?;
+    let (status, history) = state.repository.item_status_history(&order_uid, chrt_id).await?;
+
+    Ok(Json(json!({
+        "order_uid": order_uid,
+        "chrt_id": chrt_id,
+        "status": status,
+        "name": ItemStatus::from_code(status),
+        "history": history,
+    })))
+}
+
+// Асинхронная функция для перевода товара в новый статус
+pub async fn change_item_status(
+    path: Result<Path<(String, i64)>, PathRejection>, // Извлекаем UID заказа и chrt_id товара из пути запроса
+    State(state): State<AppState>, // Извлекаем состояние приложения
+    payload: Result<Json<StatusChange>, JsonRejection>, // Извлекаем новый статус из JSON
+) -> Result<impl IntoResponse, Error> {
+    let Path((order_uid, chrt_id)) = path?;
+    let Json(change) = payload?;
+
+    state.repository.transition_item_status(&order_uid, chrt_id, change.status).await?;
+
+    // Статус товара в закэшированном заказе устарел
+    state.orders.remove(&order_uid);
+    info!("Item {:?} in order {:?} changed status to {:?}", chrt_id, order_uid, change.status);
+
+    item_status(Ok(Path((order_uid, chrt_id))), State(state)).await
+}
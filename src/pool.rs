@@ -0,0 +1,233 @@
+use std::sync::atomic::{AtomicBool, Ordering}; // Признак доступности базы данных
+use std::sync::{Arc, Mutex};
+use std::time::Duration; // Для задания таймаута получения соединения
+use tokio::sync::Notify; // Уведомление о потере соединения
+use tokio_util::task::TaskTracker; // Учет задач обслуживания соединений
+use futures::future::BoxFuture;
+use tokio_postgres::{Client, Error, NoTls}; // Клиент и ошибка PostgreSQL
+use bb8::ManageConnection; // Трейт менеджера соединений пула
+use log::{info, warn, error}; // Макросы для логирования
+
+use crate::db::StatementCache; // Кэш подготовленных запросов соединения
+use crate::tls::DbTls; // Шифрование соединений с базой данных
+
+// Начальная задержка перед повторным подключением
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+// Пул соединений с базой данных
+pub type Pool = bb8::Pool<PostgresConnectionManager>;
+
+// Настройки пула соединений
+pub struct PoolConfig {
+    pub max_size: u32, // Максимальное количество соединений
+    pub min_idle: u32, // Минимальное количество простаивающих соединений
+    pub timeout: Duration, // Таймаут ожидания свободного соединения
+    pub health_check: bool, // Проверять соединение перед выдачей из пула
+    pub reconnect_max_delay: Duration, // Максимальная задержка между попытками подключения
+    pub connect_attempts: u32, // Количество попыток подключения при запуске (0 - без ограничения)
+    pub tls: DbTls, // Шифрование соединений
+}
+
+// Состояние подключения к базе данных.
+// Соединение считается потерянным, если не удалось открыть новое соединение
+// или фоновая задача соединения завершилась с ошибкой
+pub struct DatabaseHealth {
+    connected: AtomicBool, // База данных доступна
+    last_error: Mutex<Option<String>>, // Последняя ошибка соединения
+    lost: Notify, // Уведомление задачи переподключения о потере соединения
+}
+
+impl Default for DatabaseHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DatabaseHealth {
+    pub fn new() -> Self {
+        DatabaseHealth {
+            connected: AtomicBool::new(true),
+            last_error: Mutex::new(None),
+            lost: Notify::new(),
+        }
+    }
+
+    // Доступна ли база данных
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    // Последняя ошибка соединения
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    // Отметка об успешном соединении
+    fn mark_connected(&self) {
+        if !self.connected.swap(true, Ordering::AcqRel) {
+            info!("Database connection restored");
+        }
+    }
+
+    // Отметка о потере соединения, будит задачу переподключения
+    fn mark_lost(&self, e: &Error) {
+        *self.last_error.lock().unwrap() = Some(e.to_string());
+        if self.connected.swap(false, Ordering::AcqRel) {
+            warn!("Database is unavailable, service is degraded: {}", e);
+        }
+        self.lost.notify_one();
+    }
+}
+
+// Соединение пула: клиент и подготовленные на этом соединении запросы
+pub struct Connection {
+    pub client: Client, // Клиент tokio_postgres
+    pub statements: StatementCache, // Кэш подготовленных запросов
+}
+
+// Менеджер, создающий и проверяющий соединения tokio_postgres
+pub struct PostgresConnectionManager {
+    database_url: String, // URL для подключения к базе данных
+    tls: DbTls, // Шифрование соединений
+    health: Arc<DatabaseHealth>, // Состояние подключения к базе данных
+    connections: TaskTracker, // Задачи обслуживания открытых соединений
+}
+
+impl PostgresConnectionManager {
+    pub fn new(database_url: String, tls: DbTls, health: Arc<DatabaseHealth>, connections: TaskTracker) -> Self {
+        PostgresConnectionManager { database_url, tls, health, connections }
+    }
+}
+
+impl ManageConnection for PostgresConnectionManager {
+    type Connection = Connection;
+    type Error = Error;
+
+    // Открываем новое соединение
+    async fn connect(&self) -> Result<Connection, Error> {
+        let (client, connection) = open(&self.database_url, &self.tls)
+            .await
+            .inspect_err(|e| self.health.mark_lost(e))?;
+        self.health.mark_connected();
+
+        let health = self.health.clone();
+        self.connections.spawn(async move {
+            if let Err(e) = connection.await {
+                error!("Connection error: {}", e); // Логируем ошибку соединения
+                health.mark_lost(&e);
+            }
+        });
+        Ok(Connection { client, statements: StatementCache::default() })
+    }
+
+    // Проверка работоспособности соединения легковесным запросом
+    async fn is_valid(&self, connection: &mut Connection) -> Result<(), Error> {
+        connection.client.simple_query("").await.map(|_| ())
+    }
+
+    // Соединение считается сломанным, если фоновая задача соединения завершилась
+    fn has_broken(&self, connection: &mut Connection) -> bool {
+        connection.client.is_closed()
+    }
+}
+
+// Открытие соединения: возвращает клиента и задачу обслуживания соединения, которую нужно запустить
+async fn open(database_url: &str, tls: &DbTls) -> Result<(Client, BoxFuture<'static, Result<(), Error>>), Error> {
+    match tls {
+        DbTls::Disabled => {
+            let (client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
+            Ok((client, Box::pin(connection)))
+        }
+        DbTls::Rustls(connector) => {
+            let (client, connection) = tokio_postgres::connect(database_url, connector.clone()).await?;
+            Ok((client, Box::pin(connection)))
+        }
+    }
+}
+
+// Открытие одиночного соединения и запуск задачи для его обслуживания
+pub async fn connect(database_url: &str, tls: &DbTls) -> Result<Client, Error> {
+    let (client, connection) = open(database_url, tls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            error!("Connection error: {}", e); // Логируем ошибку соединения
+        }
+    });
+    Ok(client)
+}
+
+// Создание пула соединений с ожиданием минимального количества соединений.
+// Если база данных недоступна, попытки повторяются с экспоненциально растущей задержкой
+pub async fn create_pool(
+    database_url: String,
+    config: &PoolConfig,
+    health: Arc<DatabaseHealth>,
+    connections: TaskTracker,
+) -> Result<Pool, Error> {
+    info!(
+        "Creating database pool: max_size={}, min_idle={}, timeout={:?}, health_check={}",
+        config.max_size, config.min_idle, config.timeout, config.health_check
+    );
+
+    let mut delay = RECONNECT_INITIAL_DELAY;
+    let mut attempt = 1;
+    loop {
+        let result = bb8::Pool::builder()
+            .max_size(config.max_size)
+            .min_idle(config.min_idle)
+            .connection_timeout(config.timeout)
+            .test_on_check_out(config.health_check)
+            .build(PostgresConnectionManager::new(database_url.clone(), config.tls.clone(), health.clone(), connections.clone()))
+            .await;
+
+        match result {
+            Ok(pool) => return Ok(pool),
+            Err(e) if config.connect_attempts == 0 || attempt < config.connect_attempts => {
+                warn!("Failed to connect to the database (attempt {}), retrying in {:?}: {}", attempt, delay, e);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(config.reconnect_max_delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Асинхронная функция для восстановления соединения с базой данных.
+// После потери соединения пытается получить рабочее соединение из пула
+// с экспоненциально растущей задержкой, пока база данных снова не станет доступна
+pub async fn reconnect(pool: Pool, health: Arc<DatabaseHealth>, max_delay: Duration) {
+    loop {
+        health.lost.notified().await;
+
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        let mut attempt = 1;
+        while !health.is_connected() {
+            tokio::time::sleep(delay).await;
+            let result = match pool.get().await {
+                Ok(connection) => connection.client.simple_query("").await.map(|_| ()).map_err(bb8::RunError::User),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => health.mark_connected(),
+                Err(e) => {
+                    delay = (delay * 2).min(max_delay);
+                    warn!("Database reconnect attempt {} failed, retrying in {:?}: {}", attempt, delay, e);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+// Асинхронная функция для закрытия соединений с базой данных.
+// Пул должен быть последней копией: при его удалении клиенты закрывают соединения,
+// после чего ожидается завершение задач обслуживания соединений
+pub async fn close(pool: Pool, connections: TaskTracker, timeout: Duration) {
+    drop(pool);
+    connections.close();
+    match tokio::time::timeout(timeout, connections.wait()).await {
+        Ok(()) => info!("Database connections closed"),
+        Err(_) => warn!("Timed out closing database connections, {} still open", connections.len()),
+    }
+}
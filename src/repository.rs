@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+
+use crate::db::{self, AddOrderOutcome, OrderFilter}; // Модуль для работы с базой данных
+use crate::error::Error; // Тип ошибки приложения
+use crate::migrate::{self, MigrationStatus}; // Модуль миграций схемы базы данных
+use crate::model::{DeadLetter, ItemStatus, ItemStatusChange, Order}; // Модели данных
+use crate::pool::{Connection, Pool}; // Пул соединений с базой данных
+
+// Изменение заказа: получает текущий заказ и возвращает новый (или ошибку, отменяющую изменение)
+pub type OrderModifier<'a> = &'a (dyn Fn(Order) -> Result<Order, Error> + Send + Sync);
+
+// Хранилище заказов. Обработчики обращаются к данным только через этот трейт,
+// поэтому хранилище выбирается при запуске, а в тестах заменяется хранилищем в памяти
+#[async_trait]
+pub trait OrderRepository: Send + Sync {
+    // Добавление заказа, повтор заказа с тем же UID возвращает AddOrderOutcome::Duplicate
+    async fn add_order(&self, order: &Order) -> Result<AddOrderOutcome, Error>;
+
+    // Добавление пакета заказов одной транзакцией. Результат возвращается для каждого заказа в порядке пакета,
+    // ошибка всего пакета (например, недоступность базы данных) означает, что ни один заказ не записан
+    async fn add_orders(&self, orders: &[Order]) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error>;
+
+    // Получение заказа по UID
+    async fn get_order(&self, order_uid: &str) -> Result<Order, Error>;
+
+    // Список заказов по фильтрам с курсорной пагинацией
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, Error>;
+
+    // Заказы по списку UID в порядке списка, отсутствующие заказы пропускаются
+    async fn get_orders(&self, order_uids: &[String]) -> Result<Vec<Order>, Error>;
+
+    // UID самых новых заказов (по date_created) для прогрева кэша
+    async fn recent_order_uids(&self, limit: i64) -> Result<Vec<String>, Error>;
+
+    // Полная замена заказа
+    async fn update_order(&self, order: &Order) -> Result<(), Error>;
+
+    // Изменение заказа на основе текущего: чтение и запись выполняются атомарно
+    async fn modify_order(&self, order_uid: &str, modify: OrderModifier<'_>) -> Result<Order, Error>;
+
+    // Удаление заказа
+    async fn delete_order(&self, order_uid: &str) -> Result<(), Error>;
+
+    // Перевод товаров заказа с данным chrt_id в новый статус
+    async fn transition_item_status(&self, order_uid: &str, chrt_id: i64, status: ItemStatus) -> Result<(), Error>;
+
+    // Текущий статус товара и история его изменений
+    async fn item_status_history(&self, order_uid: &str, chrt_id: i64) -> Result<(i32, Vec<ItemStatusChange>), Error>;
+
+    // Сохранение отклоненного сообщения, возвращает его ID
+    async fn insert_dead_letter(&self, payload: &str, reason: &str, source: &str) -> Result<i64, Error>;
+
+    // Список отклоненных сообщений (от новых к старым)
+    async fn list_dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<DeadLetter>, Error>;
+
+    // Отклоненное сообщение по ID
+    async fn get_dead_letter(&self, id: i64) -> Result<DeadLetter, Error>;
+
+    // Обновление причины отклонения
+    async fn update_dead_letter_reason(&self, id: i64, reason: &str) -> Result<(), Error>;
+
+    // Удаление отклоненного сообщения
+    async fn delete_dead_letter(&self, id: i64) -> Result<(), Error>;
+
+    // Проверка доступности хранилища
+    async fn ping(&self) -> Result<(), Error>;
+
+    // Состояние миграций схемы
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, Error>;
+}
+
+// Хранилище заказов в PostgreSQL: запросы модуля db выполняются на соединениях из пула
+pub struct PostgresRepository {
+    pool: Pool, // Пул соединений с базой данных
+}
+
+impl PostgresRepository {
+    pub fn new(pool: Pool) -> Self {
+        PostgresRepository { pool }
+    }
+}
+
+#[async_trait]
+impl OrderRepository for PostgresRepository {
+    async fn add_order(&self, order: &Order) -> Result<AddOrderOutcome, Error> {
+        let mut connection = self.pool.get().await?;
+        let Connection { client, statements } = &mut *connection;
+        db::add_order(order, client, statements).await
+    }
+
+    async fn add_orders(&self, orders: &[Order]) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+        let mut connection = self.pool.get().await?;
+        let Connection { client, statements } = &mut *connection;
+        db::add_orders(orders, client, statements).await
+    }
+
+    async fn get_order(&self, order_uid: &str) -> Result<Order, Error> {
+        let connection = self.pool.get().await?;
+        db::get_order_by_uid(&order_uid.to_string(), &connection.client).await
+    }
+
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, Error> {
+        let connection = self.pool.get().await?;
+        db::list_orders(filter, &connection.client).await
+    }
+
+    async fn get_orders(&self, order_uids: &[String]) -> Result<Vec<Order>, Error> {
+        let connection = self.pool.get().await?;
+        db::get_orders_by_uids(order_uids, &connection.client).await
+    }
+
+    async fn recent_order_uids(&self, limit: i64) -> Result<Vec<String>, Error> {
+        let connection = self.pool.get().await?;
+        db::get_recent_order_uids(limit, &connection.client).await
+    }
+
+    async fn update_order(&self, order: &Order) -> Result<(), Error> {
+        let mut connection = self.pool.get().await?;
+        let Connection { client, statements } = &mut *connection;
+        db::update_order(order, client, statements).await
+    }
+
+    async fn modify_order(&self, order_uid: &str, modify: OrderModifier<'_>) -> Result<Order, Error> {
+        let order_uid = order_uid.to_string();
+        let mut connection = self.pool.get().await?;
+        let Connection { client, statements } = &mut *connection;
+
+        // Заказ блокируется до конца транзакции, поэтому между чтением и записью его никто не изменит
+        let mut transaction = client.transaction().await?;
+        db::lock_order(&order_uid, &transaction, statements).await?;
+        let current = db::get_order_by_uid(&order_uid, &transaction).await?;
+
+        let order = modify(current)?;
+        db::update_order(&order, &mut transaction, statements).await?;
+        transaction.commit().await?;
+
+        Ok(order)
+    }
+
+    async fn delete_order(&self, order_uid: &str) -> Result<(), Error> {
+        let mut connection = self.pool.get().await?;
+        let Connection { client, statements } = &mut *connection;
+        db::delete_order(&order_uid.to_string(), client, statements).await
+    }
+
+    async fn transition_item_status(&self, order_uid: &str, chrt_id: i64, status: ItemStatus) -> Result<(), Error> {
+        let mut connection = self.pool.get().await?;
+        let Connection { client, statements } = &mut *connection;
+        db::transition_item_status(&order_uid.to_string(), chrt_id, status, client, statements).await
+    }
+
+    async fn item_status_history(&self, order_uid: &str, chrt_id: i64) -> Result<(i32, Vec<ItemStatusChange>), Error> {
+        let connection = self.pool.get().await?;
+        db::get_item_status_history(&order_uid.to_string(), chrt_id, &connection.client).await
+    }
+
+    async fn insert_dead_letter(&self, payload: &str, reason: &str, source: &str) -> Result<i64, Error> {
+        let connection = self.pool.get().await?;
+        db::insert_dead_letter(payload, reason, source, &connection.client).await
+    }
+
+    async fn list_dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<DeadLetter>, Error> {
+        let connection = self.pool.get().await?;
+        db::list_dead_letters(limit, offset, &connection.client).await
+    }
+
+    async fn get_dead_letter(&self, id: i64) -> Result<DeadLetter, Error> {
+        let connection = self.pool.get().await?;
+        db::get_dead_letter(id, &connection.client).await
+    }
+
+    async fn update_dead_letter_reason(&self, id: i64, reason: &str) -> Result<(), Error> {
+        let connection = self.pool.get().await?;
+        db::update_dead_letter_reason(id, reason, &connection.client).await
+    }
+
+    async fn delete_dead_letter(&self, id: i64) -> Result<(), Error> {
+        let connection = self.pool.get().await?;
+        db::delete_dead_letter(id, &connection.client).await
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        let connection = self.pool.get().await?;
+        connection.client.simple_query("SELECT 1").await?;
+        Ok(())
+    }
+
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, Error> {
+        let connection = self.pool.get().await?;
+        migrate::status(&connection.client).await
+    }
+}
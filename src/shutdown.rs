@@ -0,0 +1,26 @@
+use std::time::{Duration, Instant};
+
+use axum_server::Handle;
+use log::info; // Макрос для логирования информации
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+// Ожидание сигнала завершения работы (SIGINT или SIGTERM)
+async fn wait_for_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+        _ = terminate.recv() => info!("Received SIGTERM"),
+    }
+}
+
+// Асинхронная функция для обработки сигнала завершения работы: сервер перестает принимать
+// новые соединения и ожидает завершения обрабатываемых запросов не дольше grace_period.
+// Время получения сигнала передается подписчикам (обработчику очереди сообщений)
+pub async fn listen(handle: Handle, grace_period: Duration, notify: watch::Sender<Option<Instant>>) {
+    wait_for_signal().await;
+    info!("Shutting down, waiting up to {:?} for in-flight requests", grace_period);
+
+    notify.send_replace(Some(Instant::now()));
+    handle.graceful_shutdown(Some(grace_period));
+}
@@ -0,0 +1,955 @@
+use std::collections::HashMap; // Для группировки товаров по заказам
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+use log::info; // Макрос для логирования информации
+use rusqlite::{named_params, params, Connection, OptionalExtension, Row, ToSql};
+use tokio::sync::Mutex;
+
+use crate::db::{self, AddOrderOutcome, OrderFilter}; // Фильтры списка и проверка переходов статусов
+use crate::error::Error; // Тип ошибки приложения
+use crate::metrics; // Замер времени выполнения функций модуля
+use crate::migrate::{Migration, MigrationStatus, SQLITE_MIGRATIONS}; // Миграции схемы SQLite
+use crate::model::{Order, Delivery, Payment, Item, ItemStatus, ItemStatusChange, DeadLetter}; // Модели данных
+use crate::repository::{OrderModifier, OrderRepository}; // Трейт хранилища заказов
+
+// Время ожидания блокировки файла базы данных другим процессом (например, подкомандой migrate)
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Хранилище заказов в SQLite для запуска без PostgreSQL.
+// Таблицы повторяют схему PostgreSQL, запросы выполняются на одном соединении в пуле блокирующих задач tokio
+pub struct SqliteRepository {
+    connection: Arc<Mutex<Connection>>, // Соединение с базой данных, запросы выполняются по очереди
+}
+
+// Путь к файлу базы данных из URL со схемой sqlite (None для других схем).
+// sqlite://orders.db - относительный путь, sqlite:///var/lib/orders.db - абсолютный, sqlite::memory: - база в памяти
+pub fn path_from_url(url: &str) -> Option<&str> {
+    url.strip_prefix("sqlite://").or_else(|| url.strip_prefix("sqlite:"))
+}
+
+impl SqliteRepository {
+    // Открытие (или создание) файла базы данных, ":memory:" - база данных в памяти процесса
+    pub fn open(path: &str) -> Result<Self, Error> {
+        info!("Opening SQLite database {:?}", path);
+
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        // Внешние ключи в SQLite по умолчанию не проверяются
+        connection.pragma_update(None, "foreign_keys", true)?;
+        // Журнал WAL не блокирует чтение другими процессами во время записи
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+
+        Ok(SqliteRepository { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    // Выполнение запросов на соединении в пуле блокирующих задач
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let mut connection = self.connection.clone().lock_owned().await;
+        blocking(move || f(&mut connection)).await?
+    }
+
+    // Применение всех непримененных миграций, возвращает версии примененных миграций
+    pub async fn migrate_up(&self) -> Result<Vec<i64>, Error> {
+        self.run(migrate_up).await
+    }
+
+    // Откат последних steps примененных миграций, возвращает версии откаченных миграций
+    pub async fn migrate_down(&self, steps: usize) -> Result<Vec<i64>, Error> {
+        self.run(move |connection| migrate_down(connection, steps)).await
+    }
+}
+
+// Запуск блокирующей функции в отдельном потоке
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T, Error> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| Error::Backend(e.into()))
+}
+
+#[async_trait]
+impl OrderRepository for SqliteRepository {
+    async fn add_order(&self, order: &Order) -> Result<AddOrderOutcome, Error> {
+        let order = order.clone();
+        self.run(move |connection| add_order(&order, connection)).await
+    }
+
+    async fn add_orders(&self, orders: &[Order]) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+        let orders = orders.to_vec();
+        self.run(move |connection| add_orders(&orders, connection)).await
+    }
+
+    async fn get_order(&self, order_uid: &str) -> Result<Order, Error> {
+        let order_uid = order_uid.to_string();
+        self.run(move |connection| get_order_by_uid(&order_uid, connection)).await
+    }
+
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, Error> {
+        let filter = filter.clone();
+        self.run(move |connection| list_orders(&filter, connection)).await
+    }
+
+    async fn get_orders(&self, order_uids: &[String]) -> Result<Vec<Order>, Error> {
+        let order_uids = order_uids.to_vec();
+        self.run(move |connection| get_orders_by_uids(&order_uids, connection)).await
+    }
+
+    async fn recent_order_uids(&self, limit: i64) -> Result<Vec<String>, Error> {
+        self.run(move |connection| get_recent_order_uids(limit, connection)).await
+    }
+
+    async fn update_order(&self, order: &Order) -> Result<(), Error> {
+        let order = order.clone();
+        self.run(move |connection| update_order(&order, connection)).await
+    }
+
+    async fn modify_order(&self, order_uid: &str, modify: OrderModifier<'_>) -> Result<Order, Error> {
+        // Соединение удерживается от чтения до записи, поэтому между ними заказ никто не изменит
+        let connection = self.connection.clone().lock_owned().await;
+        let order_uid = order_uid.to_string();
+        let (mut connection, current) = blocking(move || {
+            let current = get_order_by_uid(&order_uid, &connection);
+            (connection, current)
+        }).await?;
+
+        let order = modify(current?)?;
+        let updated = order.clone();
+        blocking(move || update_order(&updated, &mut connection)).await??;
+
+        Ok(order)
+    }
+
+    async fn delete_order(&self, order_uid: &str) -> Result<(), Error> {
+        let order_uid = order_uid.to_string();
+        self.run(move |connection| delete_order(&order_uid, connection)).await
+    }
+
+    async fn transition_item_status(&self, order_uid: &str, chrt_id: i64, status: ItemStatus) -> Result<(), Error> {
+        let order_uid = order_uid.to_string();
+        self.run(move |connection| transition_item_status(&order_uid, chrt_id, status, connection)).await
+    }
+
+    async fn item_status_history(&self, order_uid: &str, chrt_id: i64) -> Result<(i32, Vec<ItemStatusChange>), Error> {
+        let order_uid = order_uid.to_string();
+        self.run(move |connection| get_item_status_history(&order_uid, chrt_id, connection)).await
+    }
+
+    async fn insert_dead_letter(&self, payload: &str, reason: &str, source: &str) -> Result<i64, Error> {
+        let (payload, reason, source) = (payload.to_string(), reason.to_string(), source.to_string());
+        self.run(move |connection| insert_dead_letter(&payload, &reason, &source, connection)).await
+    }
+
+    async fn list_dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<DeadLetter>, Error> {
+        self.run(move |connection| list_dead_letters(limit, offset, connection)).await
+    }
+
+    async fn get_dead_letter(&self, id: i64) -> Result<DeadLetter, Error> {
+        self.run(move |connection| get_dead_letter(id, connection)).await
+    }
+
+    async fn update_dead_letter_reason(&self, id: i64, reason: &str) -> Result<(), Error> {
+        let reason = reason.to_string();
+        self.run(move |connection| {
+            let _timer = metrics::db_timer("update_dead_letter_reason");
+            connection.execute("UPDATE dead_letter SET reason = ?2 WHERE id = ?1", params![id, reason])?;
+            Ok(())
+        }).await
+    }
+
+    async fn delete_dead_letter(&self, id: i64) -> Result<(), Error> {
+        self.run(move |connection| {
+            let _timer = metrics::db_timer("delete_dead_letter");
+            connection.execute("DELETE FROM dead_letter WHERE id = ?1", [id])?;
+            Ok(())
+        }).await
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        self.run(|connection| {
+            connection.query_row("SELECT 1", [], |_| Ok(()))?;
+            Ok(())
+        }).await
+    }
+
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, Error> {
+        self.run(|connection| migration_status(connection)).await
+    }
+}
+
+// Функция для добавления заказа в базу данных, все вставки выполняются в одной транзакции.
+// Повторная запись заказа с тем же UID ничего не меняет и возвращает AddOrderOutcome::Duplicate
+fn add_order(order: &Order, connection: &mut Connection) -> Result<AddOrderOutcome, Error> {
+    let _timer = metrics::db_timer("add_order");
+    info!("Adding order with ID: {:?}", order.order_uid);
+
+    let transaction = connection.transaction()?;
+    let outcome = write_order(order, &transaction)?;
+    transaction.commit()?;
+
+    Ok(outcome)
+}
+
+// Функция для добавления пакета заказов в одной транзакции. Каждый заказ пишется в свою точку сохранения,
+// поэтому ошибка данных одного заказа не отменяет запись остальных
+fn add_orders(orders: &[Order], connection: &mut Connection) -> Result<Vec<Result<AddOrderOutcome, Error>>, Error> {
+    let _timer = metrics::db_timer("add_orders");
+    info!("Adding batch of {} orders", orders.len());
+
+    let mut transaction = connection.transaction()?;
+
+    let mut results = Vec::with_capacity(orders.len());
+    for order in orders {
+        let savepoint = transaction.savepoint()?;
+        match write_order(order, &savepoint) {
+            Ok(outcome) => {
+                savepoint.commit()?;
+                results.push(Ok(outcome));
+            }
+            // Точка сохранения откатывается при drop
+            Err(e @ Error::Backend(_)) => return Err(e),
+            Err(e) => results.push(Err(e)),
+        }
+    }
+
+    transaction.commit()?;
+
+    info!("Successfully added batch of {} orders", orders.len());
+    Ok(results)
+}
+
+// Функция для записи заказа в открытой транзакции
+fn write_order(order: &Order, connection: &Connection) -> Result<AddOrderOutcome, Error> {
+    // Если заказ уже существует, повтор считается успешным и ничего не меняет
+    let exists = connection.query_row(
+        "SELECT 1 FROM order_info WHERE order_uid = ?1",
+        [&order.order_uid],
+        |_| Ok(()),
+    ).optional()?.is_some();
+    if exists {
+        info!("Order with ID {:?} already exists, skipping", order.order_uid);
+        return Ok(AddOrderOutcome::Duplicate);
+    }
+
+    let delivery_id = insert_delivery(&order.delivery, connection)?;
+    insert_payment(&order.payment, connection)?;
+    insert_order(order, delivery_id, connection)?;
+    insert_items(order, connection)?;
+    // Записываем начальные статусы товаров в историю
+    insert_item_status_history(&order.order_uid, &db::item_status_changes(&[], order)?, connection)?;
+
+    info!("Successfully added order with ID: {:?}", order.order_uid);
+    Ok(AddOrderOutcome::Created)
+}
+
+// Функция для вставки информации о доставке, возвращает ID доставки
+fn insert_delivery(delivery: &Delivery, connection: &Connection) -> Result<i64, Error> {
+    let query = r#"
+        INSERT INTO delivery (name, phone, zip, city, address, region, email)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+    "#;
+    connection.execute(query, params![
+        delivery.name,
+        delivery.phone,
+        delivery.zip,
+        delivery.city,
+        delivery.address,
+        delivery.region,
+        delivery.email,
+    ])?;
+    Ok(connection.last_insert_rowid())
+}
+
+// Функция для вставки информации о платеже
+fn insert_payment(payment: &Payment, connection: &Connection) -> Result<(), Error> {
+    let query = r#"
+        INSERT INTO payment (
+            "transaction", request_id, currency, provider, amount,
+            payment_dt, bank, delivery_cost, goods_total, custom_fee
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+    "#;
+    connection.execute(query, params![
+        payment.transaction,
+        payment.request_id,
+        payment.currency,
+        payment.provider,
+        payment.amount,
+        payment.payment_dt,
+        payment.bank,
+        payment.delivery_cost,
+        payment.goods_total,
+        payment.custom_fee,
+    ])?;
+    Ok(())
+}
+
+// Функция для вставки информации о заказе
+fn insert_order(order: &Order, delivery_id: i64, connection: &Connection) -> Result<(), Error> {
+    let query = r#"
+        INSERT INTO order_info (
+            order_uid, track_number, entry, delivery_id, payment_transaction, locale,
+            internal_signature, customer_id, delivery_service, shardkey, sm_id, date_created, oof_shard, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+    "#;
+    connection.execute(query, params![
+        order.order_uid,
+        order.track_number,
+        order.entry,
+        delivery_id,
+        order.payment.transaction,
+        order.locale,
+        order.internal_signature,
+        order.customer_id,
+        order.delivery_service,
+        order.shardkey,
+        order.sm_id,
+        order.date_created,
+        order.oof_shard,
+        micros(order.created_at()),
+    ])?;
+    Ok(())
+}
+
+// Функция для вставки товаров заказа и их связи с заказом
+fn insert_items(order: &Order, connection: &Connection) -> Result<(), Error> {
+    let mut insert_item = connection.prepare_cached(r#"
+        INSERT INTO item (
+            chrt_id, track_number, price, rid, name, sale, size, total_price, nm_id, brand, status
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+    "#)?;
+    let mut insert_order_item = connection.prepare_cached("INSERT INTO order_item (order_uid, item_id) VALUES (?1, ?2)")?;
+
+    for item in &order.items {
+        let item_id = insert_item.insert(params![
+            item.chrt_id,
+            item.track_number,
+            item.price,
+            item.rid,
+            item.name,
+            item.sale,
+            item.size,
+            item.total_price,
+            item.nm_id,
+            item.brand,
+            item.status,
+        ])?;
+        insert_order_item.execute(params![order.order_uid, item_id])?;
+    }
+    Ok(())
+}
+
+// Функция для записи истории статусов товаров заказа
+fn insert_item_status_history(order_uid: &String, changes: &[db::StatusChange], connection: &Connection) -> Result<(), Error> {
+    let mut insert = connection.prepare_cached(
+        "INSERT INTO item_status_history (order_uid, chrt_id, old_status, new_status) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for (chrt_id, old_status, new_status) in changes {
+        insert.execute(params![order_uid, chrt_id, old_status, new_status])?;
+    }
+    Ok(())
+}
+
+// Функция для изменения заказа: информация о заказе, доставке и оплате обновляется,
+// набор товаров заменяется целиком, смена статусов товаров проверяется и записывается в историю.
+// Все изменения выполняются в одной транзакции
+fn update_order(order: &Order, connection: &mut Connection) -> Result<(), Error> {
+    let _timer = metrics::db_timer("update_order");
+    info!("Updating order with ID: {:?}", order.order_uid);
+
+    let transaction = connection.transaction()?;
+    let (delivery_id, old_payment_transaction) = get_order_references(&order.order_uid, &transaction)?;
+
+    // Смена статусов сохраненных товаров проверяется так же, как при переходе через transition_item_status
+    let current: Vec<(i64, i32)> = transaction.prepare(
+        "SELECT i.chrt_id, i.status FROM item i JOIN order_item oi ON i.item_id = oi.item_id WHERE oi.order_uid = ?1",
+    )?
+        .query_map([&order.order_uid], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    let changes = db::item_status_changes(&current, order)?;
+
+    // Обновляем информацию о доставке (или создаем, если ссылка была потеряна)
+    let delivery_id = match delivery_id {
+        Some(delivery_id) => {
+            let query = r#"
+                UPDATE delivery SET name = ?2, phone = ?3, zip = ?4, city = ?5, address = ?6, region = ?7, email = ?8
+                WHERE delivery_id = ?1
+            "#;
+            let delivery = &order.delivery;
+            transaction.execute(query, params![
+                delivery_id,
+                delivery.name,
+                delivery.phone,
+                delivery.zip,
+                delivery.city,
+                delivery.address,
+                delivery.region,
+                delivery.email,
+            ])?;
+            delivery_id
+        }
+        None => insert_delivery(&order.delivery, &transaction)?,
+    };
+
+    // Идентификатор оплаты является первичным ключом, поэтому при его смене
+    // создается новая оплата, а старая удаляется после переключения ссылки
+    let payment_changed = old_payment_transaction.as_ref() != Some(&order.payment.transaction);
+    if payment_changed {
+        insert_payment(&order.payment, &transaction)?;
+    } else {
+        let query = r#"
+            UPDATE payment SET
+                request_id = ?2, currency = ?3, provider = ?4, amount = ?5, payment_dt = ?6,
+                bank = ?7, delivery_cost = ?8, goods_total = ?9, custom_fee = ?10
+            WHERE "transaction" = ?1
+        "#;
+        let payment = &order.payment;
+        transaction.execute(query, params![
+            payment.transaction,
+            payment.request_id,
+            payment.currency,
+            payment.provider,
+            payment.amount,
+            payment.payment_dt,
+            payment.bank,
+            payment.delivery_cost,
+            payment.goods_total,
+            payment.custom_fee,
+        ])?;
+    }
+
+    let query = r#"
+        UPDATE order_info SET
+            track_number = ?2, entry = ?3, delivery_id = ?4, payment_transaction = ?5, locale = ?6,
+            internal_signature = ?7, customer_id = ?8, delivery_service = ?9, shardkey = ?10,
+            sm_id = ?11, date_created = ?12, oof_shard = ?13, created_at = ?14
+        WHERE order_uid = ?1
+    "#;
+    transaction.execute(query, params![
+        order.order_uid,
+        order.track_number,
+        order.entry,
+        delivery_id,
+        order.payment.transaction,
+        order.locale,
+        order.internal_signature,
+        order.customer_id,
+        order.delivery_service,
+        order.shardkey,
+        order.sm_id,
+        order.date_created,
+        order.oof_shard,
+        micros(order.created_at()),
+    ])?;
+
+    if let Some(old_payment_transaction) = old_payment_transaction.filter(|_| payment_changed) {
+        transaction.execute(r#"DELETE FROM payment WHERE "transaction" = ?1"#, [old_payment_transaction])?;
+    }
+
+    // Заменяем набор товаров заказа и записываем изменения статусов в историю
+    delete_order_items(&order.order_uid, &transaction)?;
+    insert_items(order, &transaction)?;
+    insert_item_status_history(&order.order_uid, &changes, &transaction)?;
+
+    transaction.commit()?;
+
+    info!("Successfully updated order with ID: {:?}", order.order_uid);
+    Ok(())
+}
+
+// Функция для удаления заказа вместе с доставкой, оплатой и товарами
+fn delete_order(order_uid: &String, connection: &mut Connection) -> Result<(), Error> {
+    let _timer = metrics::db_timer("delete_order");
+    info!("Deleting order with ID: {:?}", order_uid);
+
+    let transaction = connection.transaction()?;
+    let (delivery_id, payment_transaction) = get_order_references(order_uid, &transaction)?;
+
+    // Сначала удаляются товары и сам заказ (связи order_item удаляются каскадно), затем доставка и оплата
+    delete_order_items(order_uid, &transaction)?;
+    transaction.execute("DELETE FROM order_info WHERE order_uid = ?1", [order_uid])?;
+    transaction.execute("DELETE FROM delivery WHERE delivery_id = ?1", [delivery_id])?;
+    transaction.execute(r#"DELETE FROM payment WHERE "transaction" = ?1"#, [payment_transaction])?;
+
+    transaction.commit()?;
+
+    info!("Successfully deleted order with ID: {:?}", order_uid);
+    Ok(())
+}
+
+// Функция для получения ссылок заказа на доставку и оплату
+fn get_order_references(order_uid: &String, connection: &Connection) -> Result<(Option<i64>, Option<String>), Error> {
+    connection.query_row(
+        "SELECT delivery_id, payment_transaction FROM order_info WHERE order_uid = ?1",
+        [order_uid],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?
+        .ok_or_else(|| Error::order_not_found(order_uid))
+}
+
+// Функция для удаления всех товаров заказа (связи order_item удаляются каскадно)
+fn delete_order_items(order_uid: &String, connection: &Connection) -> Result<(), Error> {
+    connection.execute(
+        "DELETE FROM item WHERE item_id IN (SELECT item_id FROM order_item WHERE order_uid = ?1)",
+        [order_uid],
+    )?;
+    Ok(())
+}
+
+// Функция для перехода товаров заказа с данным chrt_id в новый статус.
+// Переход проверяется по допустимым переходам ItemStatus и записывается в историю
+fn transition_item_status(order_uid: &String, chrt_id: i64, status: ItemStatus, connection: &mut Connection) -> Result<(), Error> {
+    let _timer = metrics::db_timer("transition_item_status");
+    info!("Changing status of item {:?} in order {:?} to {:?}", chrt_id, order_uid, status);
+
+    let transaction = connection.transaction()?;
+
+    for (item_id, current) in get_item_statuses(order_uid, chrt_id, &transaction)? {
+        db::check_item_transition(chrt_id, current, status)?;
+
+        transaction.execute("UPDATE item SET status = ?2 WHERE item_id = ?1", params![item_id, status.code()])?;
+        transaction.execute(
+            "INSERT INTO item_status_history (order_uid, chrt_id, old_status, new_status) VALUES (?1, ?2, ?3, ?4)",
+            params![order_uid, chrt_id, current, status.code()],
+        )?;
+    }
+
+    transaction.commit()?;
+
+    info!("Successfully changed status of item {:?} in order {:?}", chrt_id, order_uid);
+    Ok(())
+}
+
+// Функция для получения текущего статуса товара и истории его изменений (от старых к новым)
+fn get_item_status_history(order_uid: &String, chrt_id: i64, connection: &Connection) -> Result<(i32, Vec<ItemStatusChange>), Error> {
+    let _timer = metrics::db_timer("get_item_status_history");
+    info!("Getting status history of item {:?} in order {:?}", chrt_id, order_uid);
+
+    let (_, status) = get_item_statuses(order_uid, chrt_id, connection)?[0];
+
+    let mut statement = connection.prepare(r#"
+        SELECT old_status, new_status, changed_at
+        FROM item_status_history
+        WHERE order_uid = ?1 AND chrt_id = ?2
+        ORDER BY id
+    "#)?;
+    let history = statement.query_map(params![order_uid, chrt_id], |row| {
+        Ok(ItemStatusChange {
+            old_status: row.get("old_status")?,
+            new_status: row.get("new_status")?,
+            changed_at: row.get("changed_at")?,
+        })
+    })?.collect::<Result<_, _>>()?;
+
+    Ok((status, history))
+}
+
+// Функция для получения ID и статусов товаров заказа с данным chrt_id
+fn get_item_statuses(order_uid: &String, chrt_id: i64, connection: &Connection) -> Result<Vec<(i64, i32)>, Error> {
+    let mut statement = connection.prepare(r#"
+        SELECT i.item_id, i.status
+        FROM item i
+        JOIN order_item oi ON i.item_id = oi.item_id
+        WHERE oi.order_uid = ?1 AND i.chrt_id = ?2
+        ORDER BY i.item_id
+    "#)?;
+    let items: Vec<(i64, i32)> = statement.query_map(params![order_uid, chrt_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    if items.is_empty() {
+        return Err(Error::item_not_found(order_uid, chrt_id));
+    }
+    Ok(items)
+}
+
+// SQL-запрос для получения информации о заказах и связанных данных (без товаров)
+const SELECT_ORDER: &str = r#"
+    SELECT
+        oi.order_uid, oi.track_number, oi.entry, oi.locale, oi.internal_signature,
+        oi.customer_id, oi.delivery_service, oi.shardkey, oi.sm_id, oi.date_created,
+        oi.oof_shard, d.delivery_id, d.name, d.phone, d.zip, d.city, d.address,
+        d.region, d.email, p."transaction", p.request_id, p.currency, p.provider,
+        p.amount, p.payment_dt, p.bank, p.delivery_cost, p.goods_total, p.custom_fee
+    FROM
+        order_info oi
+    JOIN
+        delivery d ON oi.delivery_id = d.delivery_id
+    JOIN
+        payment p ON oi.payment_transaction = p."transaction"
+"#;
+
+// Функция для получения заказа по уникальному идентификатору (UID)
+fn get_order_by_uid(order_uid: &String, connection: &Connection) -> Result<Order, Error> {
+    let _timer = metrics::db_timer("get_order_by_uid");
+    info!("Getting order with ID: {:?}", order_uid);
+
+    let query = format!("{} WHERE oi.order_uid = ?1", SELECT_ORDER);
+    let order = connection.query_row(&query, [order_uid], map_order_from_row).optional()?
+        .ok_or_else(|| Error::order_not_found(order_uid))?;
+
+    let mut orders = [order];
+    attach_items(&mut orders, connection)?;
+    let [order] = orders;
+
+    info!("Successfully got order with ID: {:?}", order_uid);
+    Ok(order)
+}
+
+// Функция для получения нескольких заказов по списку UID вместе с товарами.
+// Заказы возвращаются в порядке списка, отсутствующие заказы пропускаются
+fn get_orders_by_uids(order_uids: &[String], connection: &Connection) -> Result<Vec<Order>, Error> {
+    let _timer = metrics::db_timer("get_orders_by_uids");
+    info!("Getting {} orders by ID", order_uids.len());
+    if order_uids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; order_uids.len()].join(", ");
+    let query = format!("{} WHERE oi.order_uid IN ({})", SELECT_ORDER, placeholders);
+    let mut orders: Vec<Order> = connection.prepare(&query)?
+        .query_map(rusqlite::params_from_iter(order_uids), map_order_from_row)?
+        .collect::<Result<_, _>>()?;
+    attach_items(&mut orders, connection)?;
+
+    info!("Successfully got {} orders by ID", orders.len());
+    Ok(db::sort_by_uids(orders, order_uids))
+}
+
+// Момент создания заказа в микросекундах для столбца created_at, как created_at в PostgreSQL.
+// Неразобранная дата считается самой ранней (как -infinity в PostgreSQL)
+fn micros(created_at: Option<DateTime<Utc>>) -> i64 {
+    created_at.map_or(i64::MIN, |created_at| created_at.timestamp_micros())
+}
+
+// Граница диапазона дат фильтра (RFC 3339) в микросекундах, с округлением как в PostgreSQL
+fn filter_micros(param: &str, value: &str) -> Result<i64, Error> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|value| value.with_timezone(&Utc).round_subsecs(6).timestamp_micros())
+        .map_err(|_| crate::orders::invalid_param(param, format!("{:?} is not an RFC 3339 date-time", value)))
+}
+
+// Функция для получения UID самых новых заказов (по моменту создания)
+fn get_recent_order_uids(limit: i64, connection: &Connection) -> Result<Vec<String>, Error> {
+    let _timer = metrics::db_timer("get_recent_order_uids");
+    info!("Getting {:?} recent order IDs", limit);
+
+    let order_uids = connection.prepare("SELECT order_uid FROM order_info ORDER BY created_at DESC, order_uid LIMIT ?1")?
+        .query_map([limit], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(order_uids)
+}
+
+// Функция для получения списка заказов по фильтрам с курсорной пагинацией.
+// Заказы сортируются по (момент создания, order_uid), как и в PostgreSQL
+fn list_orders(filter: &OrderFilter, connection: &Connection) -> Result<Vec<Order>, Error> {
+    let _timer = metrics::db_timer("list_orders");
+    info!("Listing orders with filter: {:?}", filter);
+
+    // Условия используют именованные параметры, отсутствующий фильтр не применяется
+    let after = filter.after.as_ref().map(|(created_at, order_uid)| (micros(*created_at), order_uid));
+    let created_from = filter.created_from.as_deref().map(|value| filter_micros("created_from", value)).transpose()?;
+    let created_to = filter.created_to.as_deref().map(|value| filter_micros("created_to", value)).transpose()?;
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<(&str, &dyn ToSql)> = Vec::new();
+
+    if let Some(customer_id) = &filter.customer_id {
+        conditions.push("oi.customer_id = :customer_id");
+        params.push((":customer_id", customer_id));
+    }
+    if let Some(track_number) = &filter.track_number {
+        conditions.push("oi.track_number = :track_number");
+        params.push((":track_number", track_number));
+    }
+    if let Some(delivery_service) = &filter.delivery_service {
+        conditions.push("oi.delivery_service = :delivery_service");
+        params.push((":delivery_service", delivery_service));
+    }
+    if let Some(payment_provider) = &filter.payment_provider {
+        conditions.push("p.provider = :payment_provider");
+        params.push((":payment_provider", payment_provider));
+    }
+    // Даты сравниваются как моменты времени с учетом часового пояса
+    if let Some(created_from) = &created_from {
+        conditions.push("oi.created_at >= :created_from");
+        params.push((":created_from", created_from));
+    }
+    if let Some(created_to) = &created_to {
+        conditions.push("oi.created_at < :created_to");
+        params.push((":created_to", created_to));
+    }
+    if let Some(brand) = &filter.brand {
+        conditions.push("EXISTS (SELECT 1 FROM order_item f JOIN item i ON i.item_id = f.item_id WHERE f.order_uid = oi.order_uid AND i.brand = :brand)");
+        params.push((":brand", brand));
+    }
+    if let Some(nm_id) = &filter.nm_id {
+        conditions.push("EXISTS (SELECT 1 FROM order_item f JOIN item i ON i.item_id = f.item_id WHERE f.order_uid = oi.order_uid AND i.nm_id = :nm_id)");
+        params.push((":nm_id", nm_id));
+    }
+    // Курсор: заказы строго после последнего заказа предыдущей страницы в порядке сортировки
+    if let Some((created_at, order_uid)) = &after {
+        conditions.push(if filter.descending {
+            "(oi.created_at, oi.order_uid) < (:after_created_at, :after_order_uid)"
+        } else {
+            "(oi.created_at, oi.order_uid) > (:after_created_at, :after_order_uid)"
+        });
+        params.push((":after_created_at", created_at));
+        params.push((":after_order_uid", order_uid));
+    }
+    params.push((":limit", &filter.limit));
+
+    let direction = if filter.descending { "DESC" } else { "ASC" };
+    let query = format!(
+        "{} {} ORDER BY oi.created_at {}, oi.order_uid {} LIMIT :limit",
+        SELECT_ORDER,
+        if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) },
+        direction,
+        direction,
+    );
+
+    let mut orders: Vec<Order> = connection.prepare(&query)?
+        .query_map(params.as_slice(), map_order_from_row)?
+        .collect::<Result<_, _>>()?;
+    attach_items(&mut orders, connection)?;
+
+    info!("Successfully listed {} orders", orders.len());
+    Ok(orders)
+}
+
+// Функция для загрузки товаров сразу для нескольких заказов одним запросом
+fn attach_items(orders: &mut [Order], connection: &Connection) -> Result<(), Error> {
+    if orders.is_empty() {
+        return Ok(());
+    }
+
+    let placeholders = vec!["?"; orders.len()].join(", ");
+    let query = format!(r#"
+        SELECT
+            oi.order_uid AS item_order_uid, i.chrt_id, i.track_number, i.price, i.rid, i.name,
+            i.sale, i.size, i.total_price, i.nm_id, i.brand, i.status
+        FROM
+            item i
+        JOIN
+            order_item oi ON i.item_id = oi.item_id
+        WHERE
+            oi.order_uid IN ({})
+        ORDER BY
+            i.item_id
+    "#, placeholders);
+
+    let mut statement = connection.prepare(&query)?;
+    let rows = statement.query_map(
+        rusqlite::params_from_iter(orders.iter().map(|order| &order.order_uid)),
+        |row| Ok((row.get::<_, String>("item_order_uid")?, map_item_from_row(row)?)),
+    )?;
+
+    // Группируем товары по UID заказа
+    let mut items: HashMap<String, Vec<Item>> = HashMap::new();
+    for row in rows {
+        let (order_uid, item) = row?;
+        items.entry(order_uid).or_default().push(item);
+    }
+
+    for order in orders.iter_mut() {
+        order.items = items.remove(&order.order_uid).unwrap_or_default();
+    }
+    Ok(())
+}
+
+fn map_item_from_row(row: &Row) -> rusqlite::Result<Item> {
+    Ok(Item {
+        chrt_id: row.get("chrt_id")?,
+        track_number: row.get("track_number")?,
+        price: row.get("price")?,
+        rid: row.get("rid")?,
+        name: row.get("name")?,
+        sale: row.get("sale")?,
+        size: row.get("size")?,
+        total_price: row.get("total_price")?,
+        nm_id: row.get("nm_id")?,
+        brand: row.get("brand")?,
+        status: row.get("status")?,
+    })
+}
+
+// Маппинг заказа из строки, полученной из таблицы
+fn map_order_from_row(row: &Row) -> rusqlite::Result<Order> {
+    let delivery = Delivery {
+        name: row.get("name")?,
+        phone: row.get("phone")?,
+        zip: row.get("zip")?,
+        city: row.get("city")?,
+        address: row.get("address")?,
+        region: row.get("region")?,
+        email: row.get("email")?,
+    };
+
+    let payment = Payment {
+        transaction: row.get("transaction")?,
+        request_id: row.get("request_id")?,
+        currency: row.get("currency")?,
+        provider: row.get("provider")?,
+        amount: row.get("amount")?,
+        payment_dt: row.get("payment_dt")?,
+        bank: row.get("bank")?,
+        delivery_cost: row.get("delivery_cost")?,
+        goods_total: row.get("goods_total")?,
+        custom_fee: row.get("custom_fee")?,
+    };
+
+    Ok(Order {
+        order_uid: row.get("order_uid")?,
+        track_number: row.get("track_number")?,
+        entry: row.get("entry")?,
+        locale: row.get("locale")?,
+        internal_signature: row.get("internal_signature")?,
+        customer_id: row.get("customer_id")?,
+        delivery_service: row.get("delivery_service")?,
+        shardkey: row.get("shardkey")?,
+        sm_id: row.get("sm_id")?,
+        date_created: row.get("date_created")?,
+        oof_shard: row.get("oof_shard")?,
+        delivery,
+        payment,
+        items: Vec::new(),
+    })
+}
+
+// Функция для сохранения отклоненного сообщения с заказом
+fn insert_dead_letter(payload: &str, reason: &str, source: &str, connection: &Connection) -> Result<i64, Error> {
+    let _timer = metrics::db_timer("insert_dead_letter");
+    info!("Adding dead letter from {:?}", source);
+
+    connection.execute(
+        "INSERT INTO dead_letter (payload, reason, source) VALUES (?1, ?2, ?3)",
+        params![payload, reason, source],
+    )?;
+    let id = connection.last_insert_rowid();
+
+    info!("Successfully added dead letter with ID: {:?}", id);
+    Ok(id)
+}
+
+// SQL-запрос для выборки отклоненных сообщений
+const SELECT_DEAD_LETTER: &str = "SELECT id, payload, reason, source, created_at FROM dead_letter";
+
+// Функция для получения списка отклоненных сообщений (от новых к старым)
+fn list_dead_letters(limit: i64, offset: i64, connection: &Connection) -> Result<Vec<DeadLetter>, Error> {
+    let _timer = metrics::db_timer("list_dead_letters");
+    info!("Getting dead letters, limit: {:?}, offset: {:?}", limit, offset);
+
+    let query = format!("{} ORDER BY id DESC LIMIT ?1 OFFSET ?2", SELECT_DEAD_LETTER);
+    let dead_letters = connection.prepare(&query)?
+        .query_map([limit, offset], map_dead_letter_from_row)?
+        .collect::<Result<_, _>>()?;
+    Ok(dead_letters)
+}
+
+// Функция для получения отклоненного сообщения по ID
+fn get_dead_letter(id: i64, connection: &Connection) -> Result<DeadLetter, Error> {
+    let _timer = metrics::db_timer("get_dead_letter");
+    info!("Getting dead letter with ID: {:?}", id);
+
+    let query = format!("{} WHERE id = ?1", SELECT_DEAD_LETTER);
+    connection.query_row(&query, [id], map_dead_letter_from_row).optional()?
+        .ok_or_else(|| Error::dead_letter_not_found(id))
+}
+
+// Маппинг отклоненного сообщения из строки, полученной из таблицы
+fn map_dead_letter_from_row(row: &Row) -> rusqlite::Result<DeadLetter> {
+    Ok(DeadLetter {
+        id: row.get("id")?,
+        payload: row.get("payload")?,
+        reason: row.get("reason")?,
+        source: row.get("source")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+// Создание таблицы, в которой хранятся версии примененных миграций
+fn ensure_schema_table(connection: &Connection) -> Result<(), Error> {
+    connection.execute_batch(r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            description VARCHAR(255) NOT NULL,
+            applied_on TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%S+00', 'now'))
+        )
+    "#)?;
+    Ok(())
+}
+
+// Получение состояния всех миграций схемы SQLite. Схема только читается, как и в PostgreSQL:
+// если таблицы версий нет, все миграции считаются непримененными
+fn migration_status(connection: &Connection) -> Result<Vec<MigrationStatus>, Error> {
+    let exists = connection.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+        [],
+        |_| Ok(()),
+    ).optional()?.is_some();
+
+    let applied: HashMap<i64, String> = if exists {
+        connection.prepare("SELECT version, applied_on FROM schema_migrations")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?
+    } else {
+        HashMap::new()
+    };
+
+    Ok(SQLITE_MIGRATIONS.iter()
+        .map(|migration| MigrationStatus {
+            migration,
+            applied_on: applied.get(&migration.version).cloned(),
+        })
+        .collect())
+}
+
+// Применение всех непримененных миграций, каждая в своей транзакции
+fn migrate_up(connection: &mut Connection) -> Result<Vec<i64>, Error> {
+    ensure_schema_table(connection)?;
+    let pending: Vec<&Migration> = migration_status(connection)?
+        .into_iter()
+        .filter(|status| status.applied_on.is_none())
+        .map(|status| status.migration)
+        .collect();
+
+    let mut applied = Vec::new();
+    for migration in pending {
+        info!("Applying migration {} {}", migration.version, migration.description);
+
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration.up)?;
+        transaction.execute(
+            "INSERT INTO schema_migrations (version, description) VALUES (:version, :description)",
+            named_params! { ":version": migration.version, ":description": migration.description },
+        )?;
+        transaction.commit()?;
+
+        info!("Successfully applied migration {}", migration.version);
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+// Откат последних steps примененных миграций в обратном порядке
+fn migrate_down(connection: &mut Connection, steps: usize) -> Result<Vec<i64>, Error> {
+    let applied: Vec<&Migration> = migration_status(connection)?
+        .into_iter()
+        .filter(|status| status.applied_on.is_some())
+        .map(|status| status.migration)
+        .collect();
+
+    let mut reverted = Vec::new();
+    for migration in applied.into_iter().rev().take(steps) {
+        info!("Reverting migration {} {}", migration.version, migration.description);
+
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration.down)?;
+        transaction.execute("DELETE FROM schema_migrations WHERE version = ?1", [migration.version])?;
+        transaction.commit()?;
+
+        info!("Successfully reverted migration {}", migration.version);
+        reverted.push(migration.version);
+    }
+
+    Ok(reverted)
+}
@@ -0,0 +1,75 @@
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+use log::{info, error}; // Макросы для логирования
+use rustls::{ClientConfig, RootCertStore};
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+// Шифрование соединений с базой данных
+#[derive(Clone)]
+pub enum DbTls {
+    Disabled, // Соединения без TLS
+    Rustls(MakeRustlsConnect), // TLS через rustls
+}
+
+// Установка криптопровайдера rustls по умолчанию для всего процесса
+pub fn install_crypto_provider() {
+    // Ошибка означает, что провайдер уже установлен
+    let _ = rustls::crypto::ring::default_provider().install_default();
+}
+
+// Функция для создания TLS-коннектора к базе данных.
+// Сертификат сервера проверяется по указанному набору корневых сертификатов (CA bundle),
+// без него - по встроенному набору публичных корневых сертификатов
+pub fn db_connector(ca_cert: Option<&Path>) -> Result<DbTls, String> {
+    let mut roots = RootCertStore::empty();
+    match ca_cert {
+        Some(path) => {
+            let file = std::fs::File::open(path)
+                .map_err(|e| format!("Failed to open CA certificate {}: {}", path.display(), e))?;
+            for cert in rustls_pemfile::certs(&mut BufReader::new(file)) {
+                let cert = cert.map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+                roots.add(cert).map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+            }
+            if roots.is_empty() {
+                return Err(format!("No certificates found in {}", path.display()));
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(DbTls::Rustls(MakeRustlsConnect::new(config)))
+}
+
+// Время изменения файлов сертификата и ключа
+fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let cert = std::fs::metadata(cert).and_then(|metadata| metadata.modified()).ok()?;
+    let key = std::fs::metadata(key).and_then(|metadata| metadata.modified()).ok()?;
+    Some((cert, key))
+}
+
+// Асинхронная функция для перезагрузки сертификата HTTPS-сервера без перезапуска.
+// Файлы проверяются с заданным интервалом и перечитываются при изменении,
+// новые соединения используют обновленный сертификат
+pub async fn watch_certificate(config: RustlsConfig, cert: PathBuf, key: PathBuf, interval: Duration) {
+    let mut last_modified = modified(&cert, &key);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let current = modified(&cert, &key);
+        if current.is_none() || current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => info!("TLS certificate reloaded from {}", cert.display()),
+            Err(e) => error!("Failed to reload TLS certificate: {}", e), // Продолжаем использовать прежний сертификат
+        }
+    }
+}
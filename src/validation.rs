@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+
+use chrono::DateTime;
+use log::warn; // Макрос для логирования предупреждений
+use serde::Serialize;
+use serde_json::json;
+
+use crate::cli::PhoneFormat; // Формат телефона
+use crate::error::Error; // Тип ошибки приложения
+use crate::model::{ItemStatus, Order}; // Модели заказа и статуса товара
+
+// Поля, для которых есть правила проверки (имена используются для отключения правил)
+pub const FIELDS: &[&str] = &[
+    "date_created",
+    "delivery.email",
+    "delivery.phone",
+    "payment.amount",
+    "payment.delivery_cost",
+    "payment.goods_total",
+    "payment.custom_fee",
+    "items.price",
+    "items.sale",
+    "items.total_price",
+    "items.track_number",
+    "items.status",
+];
+
+// Нарушение правила проверки заказа
+#[derive(Serialize, Debug, Clone)]
+pub struct Violation {
+    pub field: String, // Путь к полю, например items[0].price
+    pub rule: &'static str, // Нарушенное правило
+    pub message: String, // Описание нарушения
+}
+
+// Настройки проверки заказов: правила для перечисленных в skip полей не применяются,
+// для отдельных полей задаются параметры правил
+#[derive(Debug, Clone, Default)]
+pub struct ValidationConfig {
+    skip: HashSet<String>, // Поля, проверка которых отключена
+    phone_format: PhoneFormat, // Формат delivery.phone
+    max_amount: Option<i32>, // Максимальное значение payment.amount (без ограничения, если не задано)
+}
+
+impl ValidationConfig {
+    pub fn new(skip: &[String], phone_format: PhoneFormat, max_amount: Option<i32>) -> Self {
+        for field in skip {
+            if !FIELDS.contains(&field.as_str()) {
+                warn!("Unknown validation field {:?}, known fields: {:?}", field, FIELDS);
+            }
+        }
+        ValidationConfig {
+            skip: skip.iter().cloned().collect(),
+            phone_format,
+            max_amount,
+        }
+    }
+
+    fn enabled(&self, field: &str) -> bool {
+        !self.skip.contains(field)
+    }
+}
+
+// Сборщик нарушений: правило для поля проверяется, только если поле не отключено
+struct Violations<'a> {
+    config: &'a ValidationConfig,
+    violations: Vec<Violation>,
+}
+
+impl Violations<'_> {
+    // field - имя поля в настройках, path - путь к полю в заказе
+    fn check(&mut self, field: &str, path: impl Into<String>, rule: &'static str, valid: impl FnOnce() -> bool, message: impl FnOnce() -> String) {
+        if self.config.enabled(field) && !valid() {
+            self.violations.push(Violation {
+                field: path.into(),
+                rule,
+                message: message(),
+            });
+        }
+    }
+}
+
+// Проверка бизнес-правил заказа. Возвращает все найденные нарушения сразу
+pub fn validate(order: &Order, config: &ValidationConfig) -> Result<(), Error> {
+    let mut v = Violations { config, violations: Vec::new() };
+
+    v.check("date_created", "date_created", "rfc3339",
+        || DateTime::parse_from_rfc3339(&order.date_created).is_ok(),
+        || format!("{:?} is not an RFC 3339 date-time", order.date_created));
+
+    let delivery = &order.delivery;
+    v.check("delivery.email", "delivery.email", "email",
+        || is_email(&delivery.email),
+        || format!("{:?} is not a valid email address", delivery.email));
+    v.check("delivery.phone", "delivery.phone", "phone",
+        || is_phone(&delivery.phone, config.phone_format),
+        || format!("{:?} is not a valid phone number", delivery.phone));
+
+    let payment = &order.payment;
+    for (field, value) in [
+        ("payment.amount", payment.amount),
+        ("payment.delivery_cost", payment.delivery_cost),
+        ("payment.goods_total", payment.goods_total),
+        ("payment.custom_fee", payment.custom_fee),
+    ] {
+        v.check(field, field, "non_negative", || value >= 0, || format!("must not be negative, got {}", value));
+    }
+    let expected_amount = payment.goods_total as i64 + payment.delivery_cost as i64 + payment.custom_fee as i64;
+    v.check("payment.amount", "payment.amount", "sum",
+        || payment.amount as i64 == expected_amount,
+        || format!("must be equal to goods_total + delivery_cost + custom_fee = {}, got {}", expected_amount, payment.amount));
+    if let Some(max_amount) = config.max_amount {
+        v.check("payment.amount", "payment.amount", "max",
+            || payment.amount <= max_amount,
+            || format!("must not exceed {}, got {}", max_amount, payment.amount));
+    }
+
+    for (i, item) in order.items.iter().enumerate() {
+        for (field, value) in [
+            ("items.price", item.price),
+            ("items.total_price", item.total_price),
+        ] {
+            v.check(field, format!("items[{}].{}", i, &field["items.".len()..]), "non_negative",
+                || value >= 0,
+                || format!("must not be negative, got {}", value));
+        }
+        v.check("items.sale", format!("items[{}].sale", i), "percent",
+            || (0..=100).contains(&item.sale),
+            || format!("must be between 0 and 100, got {}", item.sale));
+        v.check("items.track_number", format!("items[{}].track_number", i), "matches_order",
+            || item.track_number == order.track_number,
+            || format!("must match order track_number {:?}, got {:?}", order.track_number, item.track_number));
+        v.check("items.status", format!("items[{}].status", i), "known_status",
+            || ItemStatus::from_code(item.status).is_some(),
+            || format!("unknown item status {}", item.status));
+    }
+
+    if v.violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation {
+            message: format!("Order {} failed validation", order.order_uid),
+            details: json!({ "violations": v.violations }),
+        })
+    }
+}
+
+// Упрощенная проверка email: непустая локальная часть, один символ @, домен с точкой, без пробелов
+fn is_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() >= 2
+                && domain.split('.').all(|part| !part.is_empty())
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+// Проверка телефона: от 7 до 15 цифр, в формате E.164 с обязательным + в начале
+fn is_phone(phone: &str, format: PhoneFormat) -> bool {
+    let digits = match (phone.strip_prefix('+'), format) {
+        (Some(digits), _) => digits,
+        (None, PhoneFormat::E164) => return false,
+        (None, PhoneFormat::Lenient) => phone,
+    };
+    (7..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order() -> Order {
+        serde_json::from_str(include_str!("../test/model.json")).unwrap()
+    }
+
+    // Нарушения заказа в виде пар (путь к полю, правило)
+    fn violations(order: &Order, config: &ValidationConfig) -> Vec<(String, String)> {
+        match validate(order, config) {
+            Ok(()) => Vec::new(),
+            Err(Error::Validation { details, .. }) => details["violations"].as_array().unwrap().iter()
+                .map(|v| (v["field"].as_str().unwrap().to_string(), v["rule"].as_str().unwrap().to_string()))
+                .collect(),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    fn violation(field: &str, rule: &str) -> (String, String) {
+        (field.to_string(), rule.to_string())
+    }
+
+    #[test]
+    fn valid_order_passes() {
+        assert!(violations(&order(), &ValidationConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn date_created_must_be_rfc3339() {
+        let mut order = order();
+        order.date_created = "2021-11-26 06:22:19".to_string();
+        assert_eq!(violations(&order, &ValidationConfig::default()), [violation("date_created", "rfc3339")]);
+    }
+
+    #[test]
+    fn email_must_be_valid() {
+        for email in ["", "test", "@gmail.com", "test@gmail", "test@@gmail.com", "test@gmail..com", "te st@gmail.com"] {
+            let mut order = order();
+            order.delivery.email = email.to_string();
+            assert_eq!(violations(&order, &ValidationConfig::default()), [violation("delivery.email", "email")], "{:?}", email);
+        }
+    }
+
+    #[test]
+    fn phone_must_match_format() {
+        let e164 = ValidationConfig::default();
+        let lenient = ValidationConfig::new(&[], PhoneFormat::Lenient, None);
+
+        for phone in ["", "+", "+123456", "+1234567890123456", "+972-000-0000", "+97200000a0"] {
+            let mut order = order();
+            order.delivery.phone = phone.to_string();
+            assert_eq!(violations(&order, &e164), [violation("delivery.phone", "phone")], "{:?}", phone);
+            assert_eq!(violations(&order, &lenient), [violation("delivery.phone", "phone")], "{:?}", phone);
+        }
+
+        // Без + номер принимается только в нестрогом формате
+        let mut order = order();
+        order.delivery.phone = "9720000000".to_string();
+        assert_eq!(violations(&order, &e164), [violation("delivery.phone", "phone")]);
+        assert!(violations(&order, &lenient).is_empty());
+    }
+
+    #[test]
+    fn payment_values_must_not_be_negative() {
+        let mut order = order();
+        order.payment.delivery_cost = -1500;
+        order.payment.custom_fee = -1;
+        order.payment.goods_total = -1;
+        // Сумма совпадает с суммой частей, нарушено только правило неотрицательности
+        order.payment.amount = -1502;
+
+        assert_eq!(violations(&order, &ValidationConfig::default()), [
+            violation("payment.amount", "non_negative"),
+            violation("payment.delivery_cost", "non_negative"),
+            violation("payment.goods_total", "non_negative"),
+            violation("payment.custom_fee", "non_negative"),
+        ]);
+    }
+
+    #[test]
+    fn payment_amount_must_be_sum_of_parts() {
+        let mut order = order();
+        order.payment.amount += 1;
+        assert_eq!(violations(&order, &ValidationConfig::default()), [violation("payment.amount", "sum")]);
+    }
+
+    #[test]
+    fn payment_amount_must_not_exceed_max() {
+        let order = order();
+        let max = |max_amount| ValidationConfig::new(&[], PhoneFormat::E164, Some(max_amount));
+
+        assert!(violations(&order, &max(order.payment.amount)).is_empty());
+        assert_eq!(violations(&order, &max(order.payment.amount - 1)), [violation("payment.amount", "max")]);
+    }
+
+    #[test]
+    fn item_rules() {
+        let mut order = order();
+        order.items.push(order.items[0].clone());
+        let item = &mut order.items[1];
+        item.price = -1;
+        item.total_price = -1;
+        item.sale = 101;
+        item.track_number = "OTHER".to_string();
+        item.status = 999;
+
+        assert_eq!(violations(&order, &ValidationConfig::default()), [
+            violation("items[1].price", "non_negative"),
+            violation("items[1].total_price", "non_negative"),
+            violation("items[1].sale", "percent"),
+            violation("items[1].track_number", "matches_order"),
+            violation("items[1].status", "known_status"),
+        ]);
+    }
+
+    #[test]
+    fn all_violations_are_reported_at_once() {
+        let mut order = order();
+        order.date_created = "yesterday".to_string();
+        order.delivery.email = "test".to_string();
+        order.items[0].sale = -1;
+
+        assert_eq!(violations(&order, &ValidationConfig::default()), [
+            violation("date_created", "rfc3339"),
+            violation("delivery.email", "email"),
+            violation("items[0].sale", "percent"),
+        ]);
+    }
+
+    #[test]
+    fn skipped_fields_are_not_checked() {
+        let mut order = order();
+        order.delivery.phone = "unknown".to_string();
+        order.payment.amount = -1;
+        order.items[0].track_number = "OTHER".to_string();
+
+        // Для payment.amount отключаются все правила поля, включая сумму и максимум
+        let skip: Vec<String> = FIELDS.iter().map(|field| field.to_string()).collect();
+        let config = ValidationConfig::new(&skip, PhoneFormat::E164, Some(0));
+        assert!(violations(&order, &config).is_empty());
+
+        let config = ValidationConfig::new(&["delivery.phone".to_string(), "items.track_number".to_string()], PhoneFormat::E164, None);
+        assert_eq!(violations(&order, &config), [
+            violation("payment.amount", "non_negative"),
+            violation("payment.amount", "sum"),
+        ]);
+    }
+}
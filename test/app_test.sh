@@ -91,11 +91,15 @@ send_get_request() {
 }
 
 echo "Database reset"
-yes | sqlx database reset
+sqlx database drop -y
+sqlx database create
 
 echo "Build app"
 cargo build --release
 
+echo "Apply migrations"
+cargo run --release -- migrate up
+
 echo "Run app"
 cargo run --release &
 PID=$!
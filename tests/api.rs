@@ -0,0 +1,370 @@
+// Интеграционные тесты HTTP API, каждый тест выполняется для хранилища в памяти и SQLite
+#[macro_use]
+mod common;
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use rust_project_l0::cache::OrderCache;
+use rust_project_l0::cli::IngestMode;
+use rust_project_l0::create_router;
+use rust_project_l0::repository::OrderRepository;
+use rust_project_l0::sqlite::SqliteRepository;
+
+use common::{add, app, order, send, send_raw, state};
+
+backend_tests!(
+    add_and_get_order,
+    get_missing_order,
+    duplicate_order_is_rejected,
+    duplicate_payment_is_rejected,
+    invalid_order_is_stored_as_dead_letter,
+    replay_dead_letter,
+    idempotent_duplicate_returns_stored_order,
+    list_orders_with_cursor,
+    replace_patch_and_delete_order,
+    item_status_transitions,
+    item_status_changes_on_replace,
+    readiness,
+    bulk_load_json_array,
+    bulk_load_ndjson,
+    bulk_load_reports_each_order,
+);
+
+async fn add_and_get_order(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let order = order("round-trip");
+
+    let (status, created) = add(&app, &order).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created, serde_json::to_value(&order).unwrap());
+
+    let (status, fetched) = send(&app, Method::GET, "/get_order/round-trip", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched, created);
+}
+
+async fn get_missing_order(repository: Arc<dyn OrderRepository>) {
+    let (status, body) = send(&app(repository), Method::GET, "/get_order/missing", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+}
+
+async fn duplicate_order_is_rejected(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let order = order("duplicate");
+
+    assert_eq!(add(&app, &order).await.0, StatusCode::OK);
+    let (status, body) = add(&app, &order).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["details"]["order_uid"], "duplicate");
+}
+
+async fn duplicate_payment_is_rejected(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let first = order("payment-1");
+    let mut second = order("payment-2");
+    second.payment.transaction = first.payment.transaction.clone();
+
+    assert_eq!(add(&app, &first).await.0, StatusCode::OK);
+    let (status, body) = add(&app, &second).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["details"]["table"], "payment");
+}
+
+async fn invalid_order_is_stored_as_dead_letter(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let mut order = order("invalid");
+    order.delivery.email = "not-an-email".to_string();
+
+    let (status, body) = add(&app, &order).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_error");
+
+    let (status, dead_letters) = send(&app, Method::GET, "/dead_letters", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(dead_letters.as_array().unwrap().len(), 1);
+    assert_eq!(dead_letters[0]["source"], "http");
+
+    // Повторная обработка некорректного заказа снова отклоняется, сообщение остается
+    let id = dead_letters[0]["id"].as_i64().unwrap();
+    let (status, _) = send(&app, Method::POST, &format!("/dead_letters/{}/replay", id), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, Method::GET, &format!("/dead_letters/{}", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Некорректные параметры списка отклоняются до обращения к хранилищу
+    for query in ["limit=-1", "limit=0", "limit=1001", "offset=-1", "limit=abc"] {
+        let (status, body) = send(&app, Method::GET, &format!("/dead_letters?{}", query), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", query);
+        assert_eq!(body["code"], "validation_error");
+    }
+    for (method, uri) in [(Method::GET, "/dead_letters/abc"), (Method::POST, "/dead_letters/abc/replay")] {
+        let (status, body) = send(&app, method, uri, None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+        assert_eq!(body["code"], "validation_error");
+    }
+}
+
+async fn replay_dead_letter(repository: Arc<dyn OrderRepository>) {
+    let state = state(repository, IngestMode::Strict);
+    let payload = serde_json::to_string(&order("replayed")).unwrap();
+    let id = state.repository.insert_dead_letter(&payload, "backend unavailable", "nats").await.unwrap();
+    let app = create_router(state);
+
+    let (status, body) = send(&app, Method::POST, &format!("/dead_letters/{}/replay", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["order_uid"], "replayed");
+
+    // Обработанное сообщение удаляется
+    let (status, _) = send(&app, Method::GET, &format!("/dead_letters/{}", id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, Method::GET, "/get_order/replayed", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn idempotent_duplicate_returns_stored_order(repository: Arc<dyn OrderRepository>) {
+    let app = create_router(state(repository, IngestMode::Idempotent));
+    let order = order("idempotent");
+    let (_, stored) = add(&app, &order).await;
+
+    let mut repeated = order.clone();
+    repeated.customer_id = "changed".to_string();
+    let (status, body) = add(&app, &repeated).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, stored);
+}
+
+async fn list_orders_with_cursor(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    for (i, date) in ["2021-11-26T06:22:19Z", "2021-11-27T06:22:19Z", "2021-11-28T06:22:19Z"].into_iter().enumerate() {
+        let mut order = order(&format!("list-{}", i));
+        order.date_created = date.to_string();
+        assert_eq!(add(&app, &order).await.0, StatusCode::OK);
+    }
+
+    let (status, page) = send(&app, Method::GET, "/orders?limit=2&sort=asc", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let uids: Vec<&Value> = page["orders"].as_array().unwrap().iter().map(|order| &order["order_uid"]).collect();
+    assert_eq!(uids, [&json!("list-0"), &json!("list-1")]);
+
+    let cursor = page["next_cursor"].as_str().unwrap();
+    let (status, page) = send(&app, Method::GET, &format!("/orders?limit=2&sort=asc&cursor={}", cursor), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["orders"].as_array().unwrap().len(), 1);
+    assert_eq!(page["orders"][0]["order_uid"], "list-2");
+    assert_eq!(page["next_cursor"], Value::Null);
+
+    // Диапазон дат сравнивается с учетом часового пояса
+    let (_, page) = send(&app, Method::GET, "/orders?created_from=2021-11-27T09:00:00%2B03:00&created_to=2021-11-28T00:00:00Z", None).await;
+    let uids: Vec<&Value> = page["orders"].as_array().unwrap().iter().map(|order| &order["order_uid"]).collect();
+    assert_eq!(uids, [&json!("list-1")]);
+
+    // Фильтр по полю оплаты
+    let (_, page) = send(&app, Method::GET, "/orders?payment.provider=unknown", None).await;
+    assert_eq!(page["orders"], json!([]));
+}
+
+async fn replace_patch_and_delete_order(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let mut order = order("modify");
+    assert_eq!(add(&app, &order).await.0, StatusCode::OK);
+
+    order.customer_id = "replaced".to_string();
+    let (status, body) = send(&app, Method::PUT, "/orders/modify", Some(serde_json::to_value(&order).unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["customer_id"], "replaced");
+
+    let (status, body) = send(&app, Method::PATCH, "/orders/modify", Some(json!({ "delivery": { "city": "Moscow" } }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["delivery"]["city"], "Moscow");
+    assert_eq!(body["customer_id"], "replaced");
+
+    // UID заказа изменить нельзя
+    let (status, _) = send(&app, Method::PATCH, "/orders/modify", Some(json!({ "order_uid": "other" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = send(&app, Method::DELETE, "/orders/modify", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::GET, "/get_order/modify", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, Method::DELETE, "/orders/modify", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn item_status_transitions(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    assert_eq!(add(&app, &order("items")).await.0, StatusCode::OK);
+    let uri = "/orders/items/items/9934930/status";
+
+    // Начальный статус товара записывается в историю при добавлении заказа
+    let (_, body) = send(&app, Method::GET, uri, None).await;
+    assert_eq!(body["history"][0]["old_status"], Value::Null);
+    assert_eq!(body["history"][0]["new_status"], 202);
+
+    let (status, body) = send(&app, Method::POST, uri, Some(json!({ "status": "assembled" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], 203);
+    assert_eq!(body["history"][1]["old_status"], 202);
+
+    // Собранный товар нельзя сразу доставить
+    let (status, _) = send(&app, Method::POST, uri, Some(json!({ "status": "delivered" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(&app, Method::GET, uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "assembled");
+    assert_eq!(body["history"].as_array().unwrap().len(), 2);
+
+    // Статус в заказе тоже изменился
+    let (_, order) = send(&app, Method::GET, "/get_order/items", None).await;
+    assert_eq!(order["items"][0]["status"], 203);
+
+    let (status, _) = send(&app, Method::GET, "/orders/items/items/1/status", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Нечисловой chrt_id отклоняется ошибкой проверки в формате JSON
+    for (method, body) in [(Method::GET, None), (Method::POST, Some(json!({ "status": "assembled" })))] {
+        let (status, body) = send(&app, method, "/orders/items/items/abc/status", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "validation_error");
+    }
+}
+
+async fn item_status_changes_on_replace(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let mut order = order("replace-items");
+    assert_eq!(add(&app, &order).await.0, StatusCode::OK);
+    let uri = "/orders/replace-items/items/9934930/status";
+
+    // Допустимая смена статуса через PUT записывается в историю
+    order.items[0].status = 207;
+    let (status, _) = send(&app, Method::PUT, "/orders/replace-items", Some(serde_json::to_value(&order).unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(&app, Method::GET, uri, None).await;
+    assert_eq!(body["status"], 207);
+    assert_eq!(body["history"][1]["old_status"], 202);
+    assert_eq!(body["history"][1]["new_status"], 207);
+
+    // Из конечного статуса товар вернуть нельзя ни через PUT, ни через PATCH
+    order.items[0].status = 202;
+    let (status, _) = send(&app, Method::PUT, "/orders/replace-items", Some(serde_json::to_value(&order).unwrap())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(&app, Method::PATCH, "/orders/replace-items", Some(json!({ "items": [order.items[0]] }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (_, body) = send(&app, Method::GET, uri, None).await;
+    assert_eq!(body["status"], 207);
+    assert_eq!(body["history"].as_array().unwrap().len(), 2);
+
+    // Новый товар получает начальную запись истории
+    let mut item = order.items[0].clone();
+    item.chrt_id = 1;
+    order.items = vec![item];
+    let (status, _) = send(&app, Method::PUT, "/orders/replace-items", Some(serde_json::to_value(&order).unwrap())).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(&app, Method::GET, "/orders/replace-items/items/1/status", None).await;
+    assert_eq!(body["history"], json!([{ "old_status": null, "new_status": 202, "changed_at": body["history"][0]["changed_at"] }]));
+}
+
+async fn readiness(repository: Arc<dyn OrderRepository>) {
+    let mut state = state(repository, IngestMode::Strict);
+    let app = create_router(state.clone());
+    let (status, body) = send(&app, Method::GET, "/readyz", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["checks"]["database"]["status"], "up");
+    assert_eq!(body["checks"]["migrations"]["status"], "up");
+    assert_eq!(body["checks"]["cache"], json!({ "status": "up", "size": 0, "capacity": 100 }));
+
+    // Пока кэш не инициализирован, сервис не готов
+    state.orders = Arc::new(OrderCache::new(NonZeroUsize::new(10).unwrap()));
+    let app = create_router(state);
+    let (status, body) = send(&app, Method::GET, "/readyz", None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["checks"]["cache"]["status"], "down");
+    assert_eq!(body["checks"]["database"]["status"], "up");
+}
+
+async fn bulk_load_json_array(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    let orders: Vec<_> = (0..5).map(|i| order(&format!("bulk-{}", i))).collect();
+
+    let (status, body) = send(&app, Method::POST, "/orders/bulk", Some(json!(orders))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["summary"], json!({
+        "total": 5, "created": 5, "duplicate": 0, "conflict": 0, "invalid": 0, "failed": 0,
+    }));
+    assert_eq!(body["results"][4], json!({ "index": 4, "order_uid": "bulk-4", "status": "created" }));
+
+    let (status, fetched) = send(&app, Method::GET, "/get_order/bulk-3", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched, serde_json::to_value(&orders[3]).unwrap());
+}
+
+async fn bulk_load_ndjson(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    // Пустые строки и перевод строки в конце не обязательны
+    let body = ["ndjson-0", "ndjson-1", "ndjson-2"]
+        .map(|uid| serde_json::to_string(&order(uid)).unwrap())
+        .join("\r\n\n");
+
+    let (status, body) = send_raw(&app, Method::POST, "/orders/bulk", body).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["summary"]["created"], 3);
+
+    let (status, _) = send(&app, Method::GET, "/get_order/ndjson-2", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn bulk_load_reports_each_order(repository: Arc<dyn OrderRepository>) {
+    let app = app(repository);
+    assert_eq!(add(&app, &order("existing")).await.0, StatusCode::OK);
+
+    let mut invalid = order("invalid");
+    invalid.delivery.email = "not-an-email".to_string();
+    let mut conflict = order("conflict");
+    conflict.payment.transaction = "existing".to_string();
+    let body = [
+        serde_json::to_string(&order("new")).unwrap(),
+        serde_json::to_string(&order("existing")).unwrap(),
+        serde_json::to_string(&invalid).unwrap(),
+        "{\"order_uid\": \"broken\"}".to_string(),
+        serde_json::to_string(&conflict).unwrap(),
+        serde_json::to_string(&order("new")).unwrap(),
+    ].join("\n");
+
+    let (status, body) = send_raw(&app, Method::POST, "/orders/bulk", body).await;
+    assert_eq!(status, StatusCode::OK);
+    let statuses: Vec<_> = body["results"].as_array().unwrap().iter()
+        .map(|result| (result["order_uid"].as_str().unwrap(), result["status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(statuses, [
+        ("new", "created"),
+        ("existing", "duplicate"),
+        ("invalid", "invalid"),
+        ("broken", "invalid"),
+        ("conflict", "conflict"),
+        ("new", "duplicate"),
+    ]);
+    assert_eq!(body["results"][2]["error"]["code"], "validation_error");
+    assert_eq!(body["results"][4]["error"]["details"]["table"], "payment");
+
+    // Некорректные заказы сохранены как отклоненные сообщения
+    let (_, dead_letters) = send(&app, Method::GET, "/dead_letters", None).await;
+    assert_eq!(dead_letters.as_array().unwrap().len(), 2);
+    assert_eq!(dead_letters[0]["source"], "bulk");
+}
+
+#[tokio::test]
+async fn backend_error_details_are_not_exposed() {
+    // Хранилище без схемы: чтение заказа завершается ошибкой базы данных
+    let app = app(Arc::new(SqliteRepository::open(":memory:").unwrap()));
+
+    let (status, body) = send(&app, Method::GET, "/get_order/unavailable", None).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, json!({ "code": "internal_error", "message": "Internal server error", "details": null }));
+}
@@ -0,0 +1,182 @@
+// Общие функции интеграционных тестов: приложение с тестовым хранилищем и отправка запросов
+#![allow(dead_code, unused_macros)] // Не каждый тестовый файл использует все функции
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use serde_json::Value;
+use tokio_util::task::TaskTracker;
+use tower::ServiceExt;
+
+use rust_project_l0::cache::OrderCache;
+use rust_project_l0::cli::IngestMode;
+use rust_project_l0::memory::MemoryRepository;
+use rust_project_l0::migrate;
+use rust_project_l0::model::Order;
+use rust_project_l0::pool::{self, DatabaseHealth, PoolConfig};
+use rust_project_l0::repository::{OrderRepository, PostgresRepository};
+use rust_project_l0::sqlite::SqliteRepository;
+use rust_project_l0::tls::DbTls;
+use rust_project_l0::validation::ValidationConfig;
+use rust_project_l0::{create_router, AppState};
+
+// Пример заказа из тестовых данных
+pub fn order(order_uid: &str) -> Order {
+    let mut order: Order = serde_json::from_str(include_str!("../../test/model.json")).unwrap();
+    order.order_uid = order_uid.to_string();
+    // Идентификатор оплаты уникален, поэтому у каждого заказа свой
+    order.payment.transaction = order_uid.to_string();
+    order
+}
+
+// Запуск тестов для каждого хранилища: тест - асинхронная функция, принимающая пустое хранилище.
+// Тесты PostgreSQL выполняются, только если задана переменная окружения DATABASE_URL
+macro_rules! backend_tests {
+    ($($test:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(crate::common::memory()).await;
+                }
+            )*
+        }
+
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(crate::common::sqlite().await).await;
+                }
+            )*
+        }
+
+        mod postgres {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    let schema = crate::common::schema_name(module_path!(), stringify!($test));
+                    let Some(repository) = crate::common::postgres(&schema).await else {
+                        return;
+                    };
+                    super::$test(repository).await;
+                    crate::common::drop_schema(&schema).await;
+                }
+            )*
+        }
+    };
+}
+
+// Пустое хранилище в памяти
+pub fn memory() -> Arc<dyn OrderRepository> {
+    Arc::new(MemoryRepository::new())
+}
+
+// Пустое хранилище в базе данных SQLite в памяти с примененными миграциями
+pub async fn sqlite() -> Arc<dyn OrderRepository> {
+    let repository = SqliteRepository::open(":memory:").unwrap();
+    repository.migrate_up().await.unwrap();
+    Arc::new(repository)
+}
+
+// Имя схемы PostgreSQL для теста, например api_postgres_round_trip
+pub fn schema_name(module_path: &str, test: &str) -> String {
+    let mut name = format!("{}_{}", module_path.replace("::", "_"), test);
+    name.truncate(63); // Максимальная длина идентификатора PostgreSQL
+    name
+}
+
+// URL базы данных с путем поиска, состоящим из одной схемы
+fn schema_url(database_url: &str, schema: &str) -> String {
+    let separator = if database_url.contains('?') { '&' } else { '?' };
+    format!("{}{}options=-c%20search_path%3D{}", database_url, separator, schema)
+}
+
+// Пустое хранилище PostgreSQL в отдельной схеме с примененными миграциями, поэтому тесты
+// выполняются параллельно в одной базе данных. None, если DATABASE_URL не задан
+pub async fn postgres(schema: &str) -> Option<Arc<dyn OrderRepository>> {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping PostgreSQL test");
+        return None;
+    };
+
+    // Схема могла остаться от прерванного запуска
+    let client = pool::connect(&database_url, &DbTls::Disabled).await.unwrap();
+    client.batch_execute(&format!("DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0}", schema)).await.unwrap();
+
+    let database_url = schema_url(&database_url, schema);
+    let mut client = pool::connect(&database_url, &DbTls::Disabled).await.unwrap();
+    migrate::up(&mut client).await.unwrap();
+
+    let config = PoolConfig {
+        max_size: 4,
+        min_idle: 0,
+        timeout: Duration::from_secs(5),
+        health_check: false,
+        reconnect_max_delay: Duration::from_secs(1),
+        connect_attempts: 1,
+        tls: DbTls::Disabled,
+    };
+    let pool = pool::create_pool(database_url, &config, Arc::new(DatabaseHealth::new()), TaskTracker::new()).await.unwrap();
+    Some(Arc::new(PostgresRepository::new(pool)))
+}
+
+// Удаление схемы теста PostgreSQL после успешного выполнения
+pub async fn drop_schema(schema: &str) {
+    let database_url = std::env::var("DATABASE_URL").unwrap();
+    let client = pool::connect(&database_url, &DbTls::Disabled).await.unwrap();
+    client.batch_execute(&format!("DROP SCHEMA {} CASCADE", schema)).await.unwrap();
+}
+
+// Состояние приложения с данным хранилищем
+pub fn state(repository: Arc<dyn OrderRepository>, ingest_mode: IngestMode) -> AppState {
+    // Как и при запуске сервера, кэш считается инициализированным до начала приема запросов
+    let orders = OrderCache::new(NonZeroUsize::new(100).unwrap());
+    orders.mark_initialized();
+
+    AppState {
+        repository,
+        orders: Arc::new(orders),
+        db_health: Arc::new(DatabaseHealth::new()),
+        ingest_mode,
+        validation: Arc::new(ValidationConfig::default()),
+        bulk_batch_size: 2, // Небольшой пакет, чтобы массовая загрузка записывала несколько пакетов
+    }
+}
+
+// Маршрутизатор приложения с данным хранилищем
+pub fn app(repository: Arc<dyn OrderRepository>) -> Router {
+    create_router(state(repository, IngestMode::Strict))
+}
+
+// Отправка запроса, возвращает статус и тело ответа (Null для пустого тела)
+pub async fn send(app: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    send_raw(app, method, uri, body.map_or_else(String::new, |body| body.to_string())).await
+}
+
+// Отправка запроса с телом в виде строки
+pub async fn send_raw(app: &Router, method: Method, uri: &str, body: String) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = if bytes.is_empty() { Value::Null } else { serde_json::from_slice(&bytes).unwrap() };
+    (status, body)
+}
+
+// Добавление заказа через POST /add_order
+pub async fn add(app: &Router, order: &Order) -> (StatusCode, Value) {
+    send(app, Method::POST, "/add_order", Some(serde_json::to_value(order).unwrap())).await
+}
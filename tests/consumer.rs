@@ -0,0 +1,89 @@
+// Интеграционные тесты приема заказов из очереди сообщений через внутрипроцессный канал
+#[macro_use]
+mod common;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::sync::watch;
+
+use rust_project_l0::cli::IngestMode;
+use rust_project_l0::consumer::{self, Acknowledgement, REDELIVERY_INITIAL_DELAY};
+use rust_project_l0::repository::OrderRepository;
+use rust_project_l0::sqlite::SqliteRepository;
+
+use common::{order, state};
+
+backend_tests!(consume_orders_from_channel, payment_conflict_is_stored_as_dead_letter);
+
+async fn consume_orders_from_channel(repository: Arc<dyn OrderRepository>) {
+    let state = state(repository, IngestMode::Strict);
+    let (publisher, source) = consumer::channel(8);
+    let (shutdown, shutdown_receiver) = watch::channel(None::<Instant>);
+    let task = tokio::spawn(consumer::run(source, state.clone(), shutdown_receiver));
+
+    // Корректный заказ сохраняется и подтверждается, повтор принимается без изменений
+    let payload = serde_json::to_vec(&order("consumed")).unwrap();
+    assert_eq!(publisher.publish(payload.clone()).await.await.unwrap(), Acknowledgement::Ack);
+    assert_eq!(publisher.publish(payload).await.await.unwrap(), Acknowledgement::Ack);
+    assert_eq!(state.repository.get_order("consumed").await.unwrap().order_uid, "consumed");
+    assert!(state.orders.get("consumed").is_some());
+
+    // Некорректное сообщение подтверждается и сохраняется как отклоненное
+    assert_eq!(publisher.publish(b"not json".to_vec()).await.await.unwrap(), Acknowledgement::Ack);
+    let dead_letters = state.repository.list_dead_letters(10, 0).await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].payload, "not json");
+    assert_eq!(dead_letters[0].source, "channel");
+
+    // После сигнала завершения потребитель останавливается
+    shutdown.send(Some(Instant::now())).unwrap();
+    task.await.unwrap();
+}
+
+async fn payment_conflict_is_stored_as_dead_letter(repository: Arc<dyn OrderRepository>) {
+    let state = state(repository, IngestMode::Strict);
+    let (publisher, source) = consumer::channel(8);
+    let (_shutdown, shutdown_receiver) = watch::channel(None::<Instant>);
+    let task = tokio::spawn(consumer::run(source, state.clone(), shutdown_receiver));
+
+    // Другой заказ с тем же идентификатором оплаты подтверждается и сохраняется как отклоненный
+    let first = order("first");
+    let mut second = order("second");
+    second.payment.transaction = first.payment.transaction.clone();
+    assert_eq!(publisher.publish(serde_json::to_vec(&first).unwrap()).await.await.unwrap(), Acknowledgement::Ack);
+    let payload = serde_json::to_string(&second).unwrap();
+    assert_eq!(publisher.publish(payload.clone().into_bytes()).await.await.unwrap(), Acknowledgement::Ack);
+
+    assert!(state.repository.get_order("second").await.is_err());
+    let dead_letters = state.repository.list_dead_letters(10, 0).await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].payload, payload);
+    assert_eq!(dead_letters[0].source, "channel");
+
+    drop(publisher);
+    task.await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn backend_error_requests_redelivery() {
+    // Хранилище без схемы: сохранение заказа завершается ошибкой базы данных
+    let state = state(Arc::new(SqliteRepository::open(":memory:").unwrap()), IngestMode::Strict);
+    let (publisher, source) = consumer::channel(8);
+    let (_shutdown, shutdown_receiver) = watch::channel(None::<Instant>);
+    let task = tokio::spawn(consumer::run(source, state.clone(), shutdown_receiver));
+
+    // Сообщение не подтверждается, брокер доставит его повторно с задержкой, отклоненным оно не сохраняется
+    let payload = serde_json::to_vec(&order("unavailable")).unwrap();
+    assert_eq!(publisher.publish(payload.clone()).await.await.unwrap(), Acknowledgement::Nack(REDELIVERY_INITIAL_DELAY));
+    assert!(state.orders.get("unavailable").is_none());
+
+    // До истечения задержки новые сообщения не принимаются, при повторной ошибке задержка удваивается
+    let published = tokio::time::Instant::now();
+    assert_eq!(publisher.publish(payload).await.await.unwrap(), Acknowledgement::Nack(REDELIVERY_INITIAL_DELAY * 2));
+    assert!(published.elapsed() >= REDELIVERY_INITIAL_DELAY);
+
+    // После закрытия источника потребитель останавливается
+    drop(publisher);
+    task.await.unwrap();
+}
@@ -0,0 +1,44 @@
+// Интеграционные тесты метрик: учет обрабатываемых HTTP-запросов
+use std::future::pending;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::Request,
+    middleware,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use tower::ServiceExt;
+
+use rust_project_l0::metrics;
+
+// Значение http_requests_in_flight для маршрута из выдачи метрик
+async fn in_flight(route: &str) -> Option<i64> {
+    let response = metrics::metrics().await.into_response();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let label = format!("route=\"{}\"", route);
+
+    String::from_utf8(bytes.to_vec()).unwrap()
+        .lines()
+        .find(|line| line.starts_with("http_requests_in_flight{") && line.contains(&label))
+        .and_then(|line| line.rsplit(' ').next()?.parse().ok())
+}
+
+#[tokio::test]
+async fn aborted_request_is_not_left_in_flight() {
+    let app = Router::new()
+        .route("/pending", get(pending::<()>))
+        .route_layer(middleware::from_fn(metrics::track));
+
+    let request = Request::builder().uri("/pending").body(Body::empty()).unwrap();
+    let mut response = Box::pin(app.oneshot(request));
+
+    // Обработчик не завершается, запрос учтен как обрабатываемый
+    assert!(futures::poll!(&mut response).is_pending());
+    assert_eq!(in_flight("/pending").await, Some(1));
+
+    // Обработка прервана (например, клиент закрыл соединение): запрос больше не учитывается
+    drop(response);
+    assert_eq!(in_flight("/pending").await, Some(0));
+}
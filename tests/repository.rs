@@ -0,0 +1,152 @@
+// Интеграционные тесты чтения заказов из хранилища: загрузка по списку UID, сортировка списка и прогрев кэша
+#[macro_use]
+mod common;
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use rust_project_l0::cache::{self, OrderCache};
+use rust_project_l0::db::OrderFilter;
+use rust_project_l0::repository::OrderRepository;
+
+use common::order;
+
+backend_tests!(
+    get_orders_by_uid_list,
+    list_orders_sorts_by_moment_of_creation,
+    list_orders_within_one_millisecond,
+    warm_up_loads_most_recent_orders,
+);
+
+// Добавление заказов с датами создания по порядку (первый - самый старый)
+async fn add_orders(repository: &dyn OrderRepository, order_uids: &[&str]) {
+    for (i, order_uid) in order_uids.iter().enumerate() {
+        let mut order = order(order_uid);
+        order.date_created = format!("2021-11-{:02}T06:22:19Z", i + 1);
+        let mut item = order.items[0].clone();
+        item.chrt_id += 1;
+        order.items.push(item);
+        repository.add_order(&order).await.unwrap();
+    }
+}
+
+async fn get_orders_by_uid_list(repository: Arc<dyn OrderRepository>) {
+    add_orders(&*repository, &["a", "b", "c"]).await;
+
+    // Порядок совпадает с порядком UID в запросе, отсутствующие заказы пропускаются
+    let uids = ["c", "missing", "a"].map(String::from);
+    let orders = repository.get_orders(&uids).await.unwrap();
+    let order_uids: Vec<&str> = orders.iter().map(|order| order.order_uid.as_str()).collect();
+    assert_eq!(order_uids, ["c", "a"]);
+
+    // Заказ загружается вместе с товарами в исходном порядке
+    let stored = repository.get_order("a").await.unwrap();
+    assert_eq!(serde_json::to_value(&orders[1]).unwrap(), serde_json::to_value(&stored).unwrap());
+    assert_eq!(orders[1].items.len(), 2);
+    assert_eq!(orders[1].items[1].chrt_id, orders[1].items[0].chrt_id + 1);
+
+    assert!(repository.get_orders(&[]).await.unwrap().is_empty());
+}
+
+async fn list_orders_sorts_by_moment_of_creation(repository: Arc<dyn OrderRepository>) {
+    // Строки дат с разным смещением: по строкам порядок был бы 05:00Z, 07:00Z, 06:22Z
+    for (order_uid, date_created) in [
+        ("early", "2021-11-26T08:00:00+03:00"), // 05:00Z
+        ("middle", "2021-11-26T06:22:19Z"),
+        ("late", "2021-11-26T07:00:00Z"),
+    ] {
+        let mut order = order(order_uid);
+        order.date_created = date_created.to_string();
+        repository.add_order(&order).await.unwrap();
+    }
+
+    for (descending, expected) in [(true, ["late", "middle", "early"]), (false, ["early", "middle", "late"])] {
+        let mut filter = OrderFilter { descending, limit: 3, ..Default::default() };
+        let orders = repository.list_orders(&filter).await.unwrap();
+        let order_uids: Vec<&str> = orders.iter().map(|order| order.order_uid.as_str()).collect();
+        assert_eq!(order_uids, expected);
+
+        // Постраничный обход по курсору проходит те же заказы без пропусков и повторов
+        filter.limit = 1;
+        let mut pages = Vec::new();
+        loop {
+            let page = repository.list_orders(&filter).await.unwrap();
+            let Some(last) = page.last() else { break };
+            filter.after = Some((last.created_at(), last.order_uid.clone()));
+            pages.push(last.order_uid.clone());
+        }
+        assert_eq!(pages, expected);
+    }
+
+    let uids = repository.recent_order_uids(1).await.unwrap();
+    assert_eq!(uids, ["late"]);
+}
+
+async fn list_orders_within_one_millisecond(repository: Arc<dyn OrderRepository>) {
+    // Заказы созданы в одну миллисекунду и различаются микросекундами, дата "unknown" считается самой ранней
+    for (order_uid, date_created) in [
+        ("a", "2021-11-26T06:22:19.000300Z"),
+        ("b", "2021-11-26T09:22:19.0001+03:00"), // 06:22:19.000100Z
+        ("c", "2021-11-26T06:22:19.0002Z"),
+        ("d", "unknown"),
+    ] {
+        let mut order = order(order_uid);
+        order.date_created = date_created.to_string();
+        repository.add_order(&order).await.unwrap();
+    }
+
+    for (descending, expected) in [(false, ["d", "b", "c", "a"]), (true, ["a", "c", "b", "d"])] {
+        let mut filter = OrderFilter { descending, limit: 1, ..Default::default() };
+        let mut pages = Vec::new();
+        loop {
+            let page = repository.list_orders(&filter).await.unwrap();
+            let Some(last) = page.last() else { break };
+            filter.after = Some((last.created_at(), last.order_uid.clone()));
+            pages.push(last.order_uid.clone());
+        }
+        assert_eq!(pages, expected);
+    }
+
+    // Границы диапазона сравниваются с точностью до микросекунды
+    let filter = OrderFilter {
+        created_from: Some("2021-11-26T06:22:19.0002Z".to_string()),
+        limit: 10,
+        ..Default::default()
+    };
+    let orders = repository.list_orders(&filter).await.unwrap();
+    let order_uids: Vec<&str> = orders.iter().map(|order| order.order_uid.as_str()).collect();
+    assert_eq!(order_uids, ["c", "a"]);
+
+    let filter = OrderFilter {
+        created_to: Some("2021-11-26T06:22:19.0002Z".to_string()),
+        limit: 10,
+        ..Default::default()
+    };
+    let orders = repository.list_orders(&filter).await.unwrap();
+    let order_uids: Vec<&str> = orders.iter().map(|order| order.order_uid.as_str()).collect();
+    assert_eq!(order_uids, ["d", "b"]);
+
+    assert_eq!(repository.recent_order_uids(2).await.unwrap(), ["a", "c"]);
+}
+
+async fn warm_up_loads_most_recent_orders(repository: Arc<dyn OrderRepository>) {
+    add_orders(&*repository, &["old", "middle", "new"]).await;
+
+    // В каждом сегменте достаточно места для обоих заказов
+    let cache = OrderCache::new(NonZeroUsize::new(100).unwrap());
+    let loaded = cache::warm_up(&cache, &*repository, 2).await.unwrap();
+    assert_eq!(loaded, 2);
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get("new").is_some());
+    assert!(cache.get("middle").is_some());
+    assert!(cache.get("old").is_none());
+
+    // 10 сегментов по одному заказу: если заказы попадают в один сегмент, более старый пропускается,
+    // а не вытесняет загруженный, и возвращается количество заказов, оказавшихся в кэше
+    let cache = OrderCache::new(NonZeroUsize::new(10).unwrap());
+    let loaded = cache::warm_up(&cache, &*repository, 2).await.unwrap();
+    assert_eq!(loaded, cache.len());
+    assert!(cache.get("new").is_some());
+    assert_eq!(cache.get("middle").is_some(), loaded == 2);
+    assert!(cache.get("old").is_none());
+}
@@ -0,0 +1,98 @@
+// Интеграционные тесты хранилища SQLite: сохранение в файле и миграции схемы
+mod common;
+
+use std::path::PathBuf;
+
+use rust_project_l0::db::OrderFilter;
+use rust_project_l0::error::Error;
+use rust_project_l0::repository::OrderRepository;
+use rust_project_l0::sqlite::{self, SqliteRepository};
+
+use common::order;
+
+// Путь к файлу базы данных во временном каталоге, уникальный для теста
+fn database_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("orders-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn path_from_url() {
+    assert_eq!(sqlite::path_from_url("sqlite://orders.db"), Some("orders.db"));
+    assert_eq!(sqlite::path_from_url("sqlite:///var/lib/orders.db"), Some("/var/lib/orders.db"));
+    assert_eq!(sqlite::path_from_url("sqlite::memory:"), Some(":memory:"));
+    assert_eq!(sqlite::path_from_url("postgres://localhost/orders_db"), None);
+}
+
+#[tokio::test]
+async fn orders_persist_in_file() {
+    let path = database_path("persist");
+    let path = path.to_str().unwrap();
+
+    let repository = SqliteRepository::open(path).unwrap();
+    assert_eq!(repository.migrate_up().await.unwrap().len(), 2);
+    repository.add_order(&order("persisted")).await.unwrap();
+    drop(repository);
+
+    // После повторного открытия миграции уже применены, заказ сохранен
+    let repository = SqliteRepository::open(path).unwrap();
+    assert!(repository.migrate_up().await.unwrap().is_empty());
+    let stored = repository.get_order("persisted").await.unwrap();
+    assert_eq!(serde_json::to_value(&stored).unwrap(), serde_json::to_value(order("persisted")).unwrap());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn migrate_down_and_up() {
+    let repository = SqliteRepository::open(":memory:").unwrap();
+
+    // Без таблицы версий все миграции не применены, чтение состояния схему не меняет
+    let statuses = repository.migration_status().await.unwrap();
+    assert!(statuses.iter().all(|status| status.applied_on.is_none()));
+    assert!(repository.migrate_down(1).await.unwrap().is_empty());
+
+    let applied = repository.migrate_up().await.unwrap();
+    let statuses = repository.migration_status().await.unwrap();
+    assert!(statuses.iter().all(|status| status.applied_on.is_some()));
+
+    assert_eq!(repository.migrate_down(1).await.unwrap(), applied[applied.len() - 1..]);
+    assert_eq!(repository.migrate_up().await.unwrap(), applied[applied.len() - 1..]);
+
+    // Откат всех миграций удаляет таблицы, заказы больше не читаются
+    assert_eq!(repository.migrate_down(applied.len()).await.unwrap().len(), applied.len());
+    assert!(matches!(repository.get_order("missing").await, Err(Error::Backend(_))));
+    assert_eq!(repository.migrate_up().await.unwrap(), applied);
+}
+
+#[tokio::test]
+async fn created_at_is_filled_by_migration() {
+    let repository = SqliteRepository::open(":memory:").unwrap();
+    repository.migrate_up().await.unwrap();
+    for (order_uid, date_created) in [
+        ("a", "2021-11-26T06:22:19.000300Z"),
+        ("b", "2021-11-26T09:22:19.0001+03:00"), // 06:22:19.000100Z
+        ("c", "2021-11-26T06:22:19Z"),
+        ("d", "unknown"),
+    ] {
+        let mut order = order(order_uid);
+        order.date_created = date_created.to_string();
+        repository.add_order(&order).await.unwrap();
+    }
+
+    // Повторное применение миграции order_created_at вычисляет момент создания по date_created
+    // так же, как запись заказа
+    repository.migrate_down(1).await.unwrap();
+    repository.migrate_up().await.unwrap();
+
+    let filter = OrderFilter { limit: 10, ..Default::default() };
+    let orders = repository.list_orders(&filter).await.unwrap();
+    let order_uids: Vec<&str> = orders.iter().map(|order| order.order_uid.as_str()).collect();
+    assert_eq!(order_uids, ["d", "c", "b", "a"]);
+
+    let filter = OrderFilter { created_from: Some("2021-11-26T06:22:19.0001Z".to_string()), limit: 10, ..Default::default() };
+    let orders = repository.list_orders(&filter).await.unwrap();
+    let order_uids: Vec<&str> = orders.iter().map(|order| order.order_uid.as_str()).collect();
+    assert_eq!(order_uids, ["b", "a"]);
+}
@@ -0,0 +1,150 @@
+// Интеграционные тесты TLS с самоподписанными сертификатами, созданными в тесте:
+// набор корневых сертификатов для соединений с базой данных и перезагрузка сертификата HTTPS-сервера
+use std::io::Write;
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use rcgen::{CertifiedKey, Certificate};
+use rustls::{ClientConfig, ClientConnection, RootCertStore};
+
+use rust_project_l0::tls::{self, DbTls};
+
+// Интервал проверки изменения файлов сертификата в тесте
+const RELOAD_INTERVAL: Duration = Duration::from_millis(50);
+
+// Каталог для файлов теста во временном каталоге, уникальный для теста
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tls-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Самоподписанный сертификат для localhost
+fn self_signed() -> CertifiedKey {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+// Запись файла с заданным временем изменения (чтобы изменение было заметно при грубой точности времени файлов)
+fn write(path: &Path, contents: &str, modified: SystemTime) {
+    let mut file = std::fs::File::create(path).unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file.set_modified(modified).unwrap();
+}
+
+// Запись сертификата и ключа в файлы
+fn write_pair(dir: &Path, certified: &CertifiedKey, modified: SystemTime) -> (PathBuf, PathBuf) {
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    write(&cert, &certified.cert.pem(), modified);
+    write(&key, &certified.key_pair.serialize_pem(), modified);
+    (cert, key)
+}
+
+// TLS-рукопожатие с сервером, которому доверяет только данный сертификат
+async fn handshake(addr: SocketAddr, trusted: &Certificate) -> std::io::Result<()> {
+    let mut roots = RootCertStore::empty();
+    roots.add(trusted.der().clone()).unwrap();
+    let config = ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+
+    tokio::task::spawn_blocking(move || {
+        let mut connection = ClientConnection::new(Arc::new(config), "localhost".try_into().unwrap()).unwrap();
+        let mut socket = TcpStream::connect(addr)?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+        Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+// Ожидание, пока сервер не начнет использовать сертификат
+async fn wait_for_certificate(addr: SocketAddr, trusted: &Certificate) {
+    for _ in 0..100 {
+        if handshake(addr, trusted).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(RELOAD_INTERVAL).await;
+    }
+    panic!("Server did not switch to the new certificate");
+}
+
+#[test]
+fn db_connector_accepts_ca_bundle() {
+    tls::install_crypto_provider();
+    let dir = test_dir("ca-bundle");
+
+    // Набор из двух корневых сертификатов
+    let bundle = dir.join("ca.pem");
+    write(&bundle, &(self_signed().cert.pem() + &self_signed().cert.pem()), SystemTime::now());
+    assert!(matches!(tls::db_connector(Some(&bundle)), Ok(DbTls::Rustls(_))));
+
+    // Без набора сертификат сервера проверяется по встроенным публичным корневым сертификатам
+    assert!(matches!(tls::db_connector(None), Ok(DbTls::Rustls(_))));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn db_connector_rejects_invalid_ca_bundle() {
+    tls::install_crypto_provider();
+    let dir = test_dir("invalid-ca");
+
+    let empty = dir.join("empty.pem");
+    write(&empty, "", SystemTime::now());
+    let not_pem = dir.join("not-pem.pem");
+    write(&not_pem, "not a certificate", SystemTime::now());
+    let broken = dir.join("broken.pem");
+    write(&broken, "-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydGlmaWNhdGU=\n-----END CERTIFICATE-----\n", SystemTime::now());
+    let missing = dir.join("missing.pem");
+
+    for path in [&empty, &not_pem, &broken, &missing] {
+        let Err(e) = tls::db_connector(Some(path)) else {
+            panic!("{} must be rejected", path.display());
+        };
+        assert!(e.contains(&path.display().to_string()), "{}", e);
+    }
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn watch_certificate_reloads_rewritten_pair() {
+    tls::install_crypto_provider();
+    let dir = test_dir("reload");
+    let (first, second) = (self_signed(), self_signed());
+    let started = SystemTime::now();
+    let (cert, key) = write_pair(&dir, &first, started);
+
+    let config = RustlsConfig::from_pem_file(&cert, &key).await.unwrap();
+    tokio::spawn(tls::watch_certificate(config.clone(), cert.clone(), key.clone(), RELOAD_INTERVAL));
+
+    let handle = Handle::new();
+    tokio::spawn(
+        axum_server::bind_rustls("127.0.0.1:0".parse().unwrap(), config)
+            .handle(handle.clone())
+            .serve(Router::new().into_make_service()),
+    );
+    let addr = handle.listening().await.unwrap();
+
+    handshake(addr, &first.cert).await.unwrap();
+    assert!(handshake(addr, &second.cert).await.is_err());
+
+    // Перезаписанная пара сертификата и ключа используется для новых соединений
+    write_pair(&dir, &second, started + Duration::from_secs(1));
+    wait_for_certificate(addr, &second.cert).await;
+    assert!(handshake(addr, &first.cert).await.is_err());
+
+    // Некорректные файлы не применяются, сервер продолжает использовать прежний сертификат
+    write(&key, "not a key", started + Duration::from_secs(2));
+    tokio::time::sleep(RELOAD_INTERVAL * 4).await;
+    handshake(addr, &second.cert).await.unwrap();
+
+    handle.shutdown();
+    let _ = std::fs::remove_dir_all(dir);
+}